@@ -6,7 +6,7 @@ use rocket::{catch, catchers, get, launch, routes, Build, Request, Rocket};
 async fn dim_types(org: &str) -> Value {
     let org = org.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_all_dim_types(&org, &Storage::DB)
+        to_response(get_all_dim_types(&org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -15,7 +15,7 @@ async fn dims_by_type(r#type: &str, org: &str) -> Value {
     let org = org.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dim_names_by_type(&dim_type, &org, &Storage::DB)
+        to_response(get_dim_names_by_type(&dim_type, &org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -24,7 +24,7 @@ async fn dims_data_by_type(r#type: &str, org: &str) -> Value {
     let org = org.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dims_data_by_type(&dim_type, &org, &Storage::DB)
+        to_response(get_dims_data_by_type(&dim_type, &org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -34,7 +34,7 @@ async fn dim_by_name(r#type: &str, name: &str, org: &str, context: Option<String
     let dim_name = name.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dim_by_name(&dim_type, &dim_name, &org, &Storage::DB, context)
+        to_response(get_dim_by_name(&dim_type, &dim_name, &org, &Storage::DB, context))
     }).await.unwrap()
 }
 
@@ -43,7 +43,7 @@ async fn dim_defaults_by_type(r#type: &str, org: &str) -> Value {
     let org = org.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dim_defaults_by_type(&dim_type, &org, &Storage::DB)
+        to_response(get_dim_defaults_by_type(&dim_type, &org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -53,7 +53,7 @@ async fn dim_parent(r#type: &str, name: &str, org: &str) -> Value {
     let dim_name = name.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dim_parent(&dim_type, &dim_name, &org, &Storage::DB)
+        to_response(get_dim_parent(&dim_type, &dim_name, &org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -63,7 +63,7 @@ async fn dims_by_parent(r#type: &str, name: &str, org: &str) -> Value {
     let dim_name = name.to_string();
     let dim_type = r#type.to_string();
     rocket::tokio::task::spawn_blocking(move || {
-        get_dim_kids(&dim_type, &dim_name, &org, &Storage::DB)
+        to_response(get_dim_kids(&dim_type, &dim_name, &org, &Storage::DB))
     }).await.unwrap()
 }
 
@@ -71,10 +71,21 @@ async fn dims_by_parent(r#type: &str, name: &str, org: &str) -> Value {
 async fn all_orgs(//key: ApiKey<'_> // <- Here we use our ApiKey guard
 ) -> Value {
     rocket::tokio::task::spawn_blocking(move || {
-        get_all_orgs(&Storage::DB)
+        to_response(get_all_orgs(&Storage::DB))
     }).await.unwrap()
 }
 
+// Library errors are returned as error json instead of stopping the service
+fn to_response(result: CubteraResult<Value>) -> Value {
+    result.unwrap_or_else(|e| {
+        json!({
+            "status": "error",
+            "message": e.to_string(),
+            "data": Value::Null,
+        })
+    })
+}
+
 #[catch(404)]
 fn not_found(req: &Request) -> String {
     format!("Sorry, '{}' is not a valid path.", req.uri())
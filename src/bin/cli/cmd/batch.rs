@@ -0,0 +1,127 @@
+// Shared parts of commands running a unit many times: run-all and run --matrix
+use clap::{value_parser, Arg};
+use cubtera::core::unit::graph::{UnitRun, UnitRunStatus};
+use cubtera::prelude::*;
+use cubtera::utils::process::{run_process, ProcessOptions};
+use std::time::Duration;
+
+// Grace period for unit processes, they forward signals to runners and wait for them by themselves
+const GRACE_PERIOD: Duration = Duration::from_secs(600);
+// Exit code of batch runs with plan changes, same as terraform detailed exit code
+const CHANGES_EXIT_CODE: i32 = 2;
+
+pub fn get_parallelism_arg() -> Arg {
+    Arg::new("parallelism")
+        .help("Number of unit runs at once")
+        .short('p')
+        .long("parallelism")
+        .value_name("number")
+        .value_parser(value_parser!(usize))
+        .default_value("1")
+}
+
+pub fn get_on_failure_arg() -> Arg {
+    Arg::new("on_failure")
+        .help("Stop starting new runs or continue with runs not depending on the failed one")
+        .long("on-failure")
+        .value_name("policy")
+        .value_parser(["stop", "continue"])
+        .default_value("stop")
+}
+
+// Runs the unit with `cubtera run` in a separate process, so every run has its own runner state.
+// Plan with changes exits with the unit drift_exit_code runner param
+pub fn run_unit_process(
+    unit: &str,
+    dimensions: &[String],
+    extensions: &[String],
+    context: Option<&String>,
+    command: &[String],
+    drift_exit_code: i32,
+) -> UnitRunStatus {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            error!(target: "", "Can't get cubtera executable path: {e}");
+            return UnitRunStatus::Failed(1);
+        }
+    };
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(["run", "-u", unit]);
+    dimensions.iter().for_each(|dim| {
+        cmd.args(["-d", dim]);
+    });
+    extensions.iter().for_each(|ext| {
+        cmd.args(["-e", ext]);
+    });
+    if let Some(context) = context {
+        cmd.args(["-c", context]);
+    }
+    if !command.is_empty() {
+        cmd.arg("--").args(command);
+    }
+
+    let options = ProcessOptions {
+        grace_period: GRACE_PERIOD,
+        ..Default::default()
+    };
+    let plan = command.first().is_some_and(|c| c == "plan");
+    match run_process(&mut cmd, &options) {
+        Ok(result) if plan && drift_exit_code != 0 && result.code() == drift_exit_code => {
+            UnitRunStatus::Changes
+        }
+        Ok(result) if result.success() => UnitRunStatus::Success,
+        Ok(result) => UnitRunStatus::Failed(result.code()),
+        Err(e) => {
+            error!(target: "", "Can't run unit {unit}: {e}");
+            UnitRunStatus::Failed(1)
+        }
+    }
+}
+
+// Prints summary table. Any failed run is an error, plan changes without failures exit with 2
+pub fn report(title: &str, results: &[UnitRun]) -> CubteraResult<()> {
+    print_summary(title, results);
+
+    let failed = results
+        .iter()
+        .filter(|r| matches!(r.status, UnitRunStatus::Failed(_)))
+        .count();
+    if failed > 0 {
+        return Err(CubteraError::Runner(format!(
+            "{failed} of {} runs failed",
+            results.len()
+        )));
+    }
+    if results.iter().any(|r| r.status == UnitRunStatus::Changes) {
+        std::process::exit(CHANGES_EXIT_CODE);
+    }
+    Ok(())
+}
+
+fn print_summary(title: &str, results: &[UnitRun]) {
+    use yansi::Paint; // don't move this to the top - conflicts with a crate
+
+    let width = results
+        .iter()
+        .map(|r| r.name.len())
+        .max()
+        .unwrap_or(0)
+        .max(title.len());
+    let header = format!("{:<width$}  {:>8}  STATUS", title, "TIME");
+    println!("\n{}", header.bold());
+    for run in results {
+        let status = match &run.status {
+            UnitRunStatus::Success => run.status.green().to_string(),
+            UnitRunStatus::Changes => run.status.yellow().to_string(),
+            UnitRunStatus::Failed(_) => run.status.red().to_string(),
+            UnitRunStatus::Skipped(_) => run.status.dim().to_string(),
+        };
+        println!(
+            "{:<width$}  {:>7}s  {}",
+            run.name,
+            run.duration.as_secs(),
+            status
+        );
+    }
+}
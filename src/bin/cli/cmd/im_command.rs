@@ -92,7 +92,7 @@ pub fn get_command() -> Command {
 }
 
 #[allow(clippy::needless_pass_by_value, clippy::too_many_lines)]
-pub fn run(subcommand: &ArgMatches, storage: &Storage) {
+pub fn run(subcommand: &ArgMatches, storage: &Storage) -> CubteraResult<()> {
     match subcommand.subcommand() {
         Some(("getAll", sub_sub_matches)) => {
             let dim_type = sub_sub_matches
@@ -101,7 +101,7 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
                 .to_string();
             println!(
                 "{}",
-                get_dim_names_by_type(&dim_type, &GLOBAL_CFG.org, storage)
+                get_dim_names_by_type(&dim_type, &GLOBAL_CFG.org, storage)?
             );
         }
         Some(("getAllData", sub_sub_matches)) => {
@@ -111,7 +111,7 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
                 .to_string();
             println!(
                 "{}",
-                get_dims_data_by_type(&dim_type, &GLOBAL_CFG.org, storage)
+                get_dims_data_by_type(&dim_type, &GLOBAL_CFG.org, storage)?
             );
         }
         Some(("getDefaults", sub_sub_matches)) => {
@@ -121,7 +121,7 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
                 .to_string();
             println!(
                 "{}",
-                get_dim_defaults_by_type(&dim_type, &GLOBAL_CFG.org, storage)
+                get_dim_defaults_by_type(&dim_type, &GLOBAL_CFG.org, storage)?
             );
         }
         Some(("getByName", sub_sub_matches)) => {
@@ -131,13 +131,13 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
                 &GLOBAL_CFG.org,
                 storage,
                 sub_sub_matches.get_one::<String>("context").cloned(),
-            );
+            )?;
             println!("{dim}");
         }
         Some(("getByParent", sub_sub_matches)) => {
             let dim_type = sub_sub_matches.get_one::<String>("dim_type").unwrap();
             let dim_name = sub_sub_matches.get_one::<String>("dim_name").unwrap();
-            let dims = get_dim_kids(dim_type, dim_name, &GLOBAL_CFG.org, storage);
+            let dims = get_dim_kids(dim_type, dim_name, &GLOBAL_CFG.org, storage)?;
 
             println!("{dims}");
         }
@@ -147,12 +147,12 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
                 sub_sub_matches.get_one::<String>("dim_name").unwrap(),
                 &GLOBAL_CFG.org,
                 storage,
-            );
+            )?;
 
             println!("{parent}");
         }
         Some(("getOrgs", _)) => {
-            println!("{}", get_all_orgs(storage));
+            println!("{}", get_all_orgs(storage)?);
         }
         Some(("syncDefaults", sub_sub_matches)) => {
             let dim_type = sub_sub_matches
@@ -163,7 +163,7 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
             DimBuilder::new(&dim_type, &GLOBAL_CFG.org, &Storage::FS)
                 .read_default_data()
                 .switch_datasource(&Storage::DB)
-                .save_default_data();
+                .save_default_data()?;
         }
 
         Some(("syncAll", sub_sub_matches)) => {
@@ -174,25 +174,25 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
 
             DimBuilder::new(&dim_type, &GLOBAL_CFG.org, &Storage::FS)
                 .with_context(sub_sub_matches.get_one::<String>("context").cloned())
-                .save_all_data_by_type();
+                .save_all_data_by_type()?;
         }
 
         Some(("sync", sub_sub_matches)) => {
             let dim_type = sub_sub_matches.get_one::<String>("dim_type").unwrap();
             DimBuilder::new(dim_type, &GLOBAL_CFG.org, &Storage::FS)
                 .with_name(sub_sub_matches.get_one::<String>("dim_name").unwrap())
-                .read_data()
+                .read_data()?
                 //.read_default_data()
                 .switch_datasource(&Storage::DB)
                 .with_context(sub_sub_matches.get_one::<String>("context").cloned())
-                .save_data();
+                .save_data()?;
         }
 
         Some(("deleteContext", sub_sub_matches)) => {
             let context = sub_sub_matches.get_one::<String>("context").cloned();
             DimBuilder::new("", &GLOBAL_CFG.org, &Storage::DB)
                 .with_context(context)
-                .delete_all_data_by_context();
+                .delete_all_data_by_context()?;
         }
 
         Some(("validate", sub_sub_matches)) => {
@@ -200,11 +200,12 @@ pub fn run(subcommand: &ArgMatches, storage: &Storage) {
             let dim_name = sub_sub_matches.get_one::<String>("dim_name").unwrap();
             let _ = DimBuilder::new(dim_type, &GLOBAL_CFG.org, &Storage::FS)
                 .with_name(dim_name)
-                .read_data()
-                .build();
+                .read_data()?
+                .build()?;
 
             println!("Sorry, validate command was not implemented yet...");
         }
         _ => unreachable!(),
     };
+    Ok(())
 }
@@ -30,7 +30,7 @@ pub fn get_command() -> Command {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-pub fn run(subcommand: &ArgMatches, _: &Storage) {
+pub fn run(subcommand: &ArgMatches, _: &Storage) -> CubteraResult<()> {
     match subcommand.subcommand() {
         Some(("get", matches)) => {
             let keys = matches
@@ -45,9 +45,10 @@ pub fn run(subcommand: &ArgMatches, _: &Storage) {
                 .unwrap_or(&"10".to_string())
                 .parse()
                 .unwrap_or(10);
-            let res = get_dlog_by_keys(&GLOBAL_CFG.org, keys.to_vec(), Some(limit));
+            let res = get_dlog_by_keys(&GLOBAL_CFG.org, keys.to_vec(), Some(limit))?;
             println!("{res}");
         }
         _ => unreachable!(),
     }
+    Ok(())
 }
@@ -2,8 +2,11 @@ use cubtera::core::dim::data::Storage;
 use cubtera::prelude::*;
 
 use clap::{command, ArgMatches};
+mod batch;
 mod im_command;
 mod log_command;
+mod render_command;
+mod run_all_command;
 mod run_command;
 
 // custom result type
@@ -25,6 +28,8 @@ pub fn get_matches() -> ArgMatches {
         .subcommand(im_command::get_command())
         .subcommand(log_command::get_command())
         .subcommand(run_command::get_command())
+        .subcommand(run_all_command::get_command())
+        .subcommand(render_command::get_command())
         .subcommand(command!("config").about("Show configuration").alias("cfg"))
         .get_matches()
 }
@@ -36,7 +41,7 @@ pub struct Cli {
     executor: CliExecutor,
 }
 
-type CliExecutor = fn(subcommand: &ArgMatches, storage: &Storage) -> ();
+type CliExecutor = fn(subcommand: &ArgMatches, storage: &Storage) -> CubteraResult<()>;
 
 #[allow(clippy::unnecessary_wraps)]
 pub fn get_args() -> CliResult<Cli> {
@@ -71,6 +76,16 @@ pub fn get_args() -> CliResult<Cli> {
             executor: run_command::run,
             storage,
         },
+        Some(("run-all", sub_matches)) => Cli {
+            subcommand: sub_matches.clone(),
+            executor: run_all_command::run,
+            storage,
+        },
+        Some(("render", sub_matches)) => Cli {
+            subcommand: sub_matches.clone(),
+            executor: render_command::run,
+            storage,
+        },
         Some(("config", _)) => {
             println!("{}", &GLOBAL_CFG.get_json());
             std::process::exit(0);
@@ -79,10 +94,17 @@ pub fn get_args() -> CliResult<Cli> {
     })
 }
 
+// Library errors are reported here: skipped unit is a warning with 0 exit code, others exit with 1
 #[allow(clippy::unnecessary_wraps)]
 pub fn run(cli: Cli) -> CliResult<()> {
-    (cli.executor)(&cli.subcommand, &cli.storage);
-    Ok(())
+    match (cli.executor)(&cli.subcommand, &cli.storage) {
+        Ok(()) => Ok(()),
+        Err(CubteraError::Skipped(e)) => {
+            warn!(target: "", "{e}");
+            Ok(())
+        }
+        Err(e) => exit_with_error(e.to_string()),
+    }
 }
 
 // Value parser for cli arguments
@@ -0,0 +1,130 @@
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use cubtera::core::dim::data::Storage;
+use cubtera::prelude::*;
+use cubtera::utils::snapshot::FolderSnapshot;
+
+pub fn get_command() -> Command {
+    Command::new("render")
+        .about("Render unit files with generated ones to <temp folder>.render without running it")
+        .arg(
+            Arg::new("dim")
+                .action(ArgAction::Append)
+                .help("Dimension type and name")
+                .short('d')
+                .long("dim")
+                .value_name("dim_type:dim_name")
+                .number_of_values(1)
+                .value_parser(super::if_contains(":"))
+                .required(true),
+        )
+        .arg(
+            Arg::new("ext")
+                .action(ArgAction::Append)
+                .help("Extension type and name (opt)")
+                .short('e')
+                .long("ext")
+                .value_name("ext_type:ext_name")
+                .number_of_values(1)
+                .value_parser(super::if_contains(":"))
+                .required(false),
+        )
+        .arg(
+            Arg::new("unit")
+                .short('u')
+                .long("unit")
+                .value_name("name")
+                .help("Unit name")
+                .required(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::new("context")
+                .help("Context (opt), advanced feature, see docs for more info.")
+                .value_name("context")
+                .required(false)
+                .short('c')
+                .long("context"),
+        )
+        .arg(
+            Arg::new("show")
+                .help("Print content of generated cubtera_* files")
+                .long("show")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("diff")
+                .help("Print changes against the previous render")
+                .long("diff")
+                .action(ArgAction::SetTrue),
+        )
+}
+
+pub fn run(sub_matches: &ArgMatches, storage: &Storage) -> CubteraResult<()> {
+    use yansi::Paint; // don't move this to the top - conflicts with a crate
+
+    let get_list = |id: &str| {
+        sub_matches
+            .get_many::<String>(id)
+            .unwrap_or_default()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>()
+    };
+    let dimensions = get_list("dim");
+    let extensions = get_list("ext");
+    let unit_name = sub_matches.get_one::<String>("unit").unwrap().clone();
+    let context = sub_matches.get_one::<String>("context").cloned();
+
+    let mut unit = Unit::new(unit_name, &dimensions, &extensions, storage, context)?.build()?;
+    // render goes to a sibling folder, so .terraform and saved plans of the unit temp folder are kept
+    let folder_name = unit
+        .temp_folder
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    let temp_folder = unit
+        .temp_folder
+        .with_file_name(format!("{folder_name}.render"));
+    unit.temp_folder = temp_folder.clone();
+    let previous = FolderSnapshot::read(&temp_folder);
+
+    RunnerBuilder::new(unit, vec![])
+        .build()?
+        .render()
+        .or_error(CubteraError::Runner, "Unit render failed".to_string())?;
+
+    let rendered = FolderSnapshot::read(&temp_folder);
+    println!("{}", temp_folder.to_string_lossy().bold());
+    for path in rendered.paths() {
+        println!("  {path}");
+    }
+
+    if sub_matches.get_flag("show") {
+        for path in rendered
+            .paths()
+            .into_iter()
+            .filter(|p| p.starts_with("cubtera_"))
+        {
+            println!("\n{}", format!("# {path}").bold());
+            println!("{}", rendered.text(path).unwrap_or("<binary or big file>"));
+        }
+    }
+
+    if sub_matches.get_flag("diff") {
+        let diff = previous.diff(&rendered);
+        println!("\n{}", "Changes against the previous render:".bold());
+        if diff.is_empty() {
+            println!("  no changes");
+        }
+        for line in diff {
+            let line = match line.trim_start().chars().next() {
+                Some('+') => line.green().to_string(),
+                Some('-') => line.red().to_string(),
+                Some('~') => line.yellow().to_string(),
+                _ => line,
+            };
+            println!("  {line}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use super::batch;
+use clap::{Arg, ArgAction, ArgMatches, Command};
+use cubtera::core::dim::data::Storage;
+use cubtera::core::unit::graph::{FailurePolicy, UnitGraph, UnitRun, UnitRunStatus};
+use cubtera::prelude::*;
+use std::time::Duration;
+
+pub fn get_command() -> Command {
+    Command::new("run-all")
+        .about("Run units with dimension's values in dependency order")
+        .arg(
+            Arg::new("dim")
+                .action(ArgAction::Append)
+                .help("Dimension type and name")
+                .short('d')
+                .long("dim")
+                .value_name("dim_type:dim_name")
+                .number_of_values(1)
+                .value_parser(super::if_contains(":"))
+                .required(true),
+        )
+        .arg(
+            Arg::new("ext")
+                .action(ArgAction::Append)
+                .help("Extension type and name (opt)")
+                .short('e')
+                .long("ext")
+                .value_name("ext_type:ext_name")
+                .number_of_values(1)
+                .value_parser(super::if_contains(":"))
+                .required(false),
+        )
+        .arg(
+            Arg::new("unit")
+                .action(ArgAction::Append)
+                .short('u')
+                .long("unit")
+                .value_name("name")
+                .help("Unit name (opt), all units accepting provided dimensions if not set")
+                .number_of_values(1)
+                .required(false),
+        )
+        .arg(
+            Arg::new("context")
+                .help("Context (opt), advanced feature, see docs for more info.")
+                .value_name("context")
+                .required(false)
+                .short('c')
+                .long("context"),
+        )
+        .arg(batch::get_parallelism_arg())
+        .arg(batch::get_on_failure_arg())
+        .arg(
+            Arg::new("command")
+                .last(true)
+                .help("Runner command (opt)")
+                .required(false)
+                .action(ArgAction::Append)
+                .allow_hyphen_values(true),
+        )
+}
+
+pub fn run(sub_matches: &ArgMatches, storage: &Storage) -> CubteraResult<()> {
+    use yansi::Paint; // don't move this to the top - conflicts with a crate
+
+    let get_list = |id: &str| {
+        sub_matches
+            .get_many::<String>(id)
+            .unwrap_or_default()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>()
+    };
+    let dimensions = get_list("dim");
+    let extensions = get_list("ext");
+    let command = get_list("command");
+    let context = sub_matches.get_one::<String>("context").cloned();
+    let parallelism = *sub_matches.get_one::<usize>("parallelism").unwrap();
+    let policy = FailurePolicy::str_to_policy(sub_matches.get_one::<String>("on_failure").unwrap());
+
+    let mut unit_names = get_list("unit");
+    let all_units = unit_names.is_empty();
+    if all_units {
+        unit_names = Unit::list_names(&dimensions)?;
+    }
+    let mut seen = std::collections::HashSet::new();
+    unit_names.retain(|name| seen.insert(name.clone()));
+
+    // units are checked before the run, not allowed ones are skipped
+    let mut units = Vec::new();
+    let mut skipped = Vec::new();
+    let mut drift_exit_codes = std::collections::HashMap::new();
+    for name in unit_names {
+        match Unit::new(
+            name.clone(),
+            &dimensions,
+            &extensions,
+            storage,
+            context.clone(),
+        )
+        .and_then(Unit::build)
+        {
+            Ok(unit) => {
+                let depends_on = unit.manifest.depends_on.clone().unwrap_or_default();
+                drift_exit_codes.insert(
+                    name.clone(),
+                    RunnerBuilder::new(unit, command.clone()).get_drift_exit_code()?,
+                );
+                units.push((name, depends_on));
+            }
+            Err(CubteraError::Skipped(e)) if all_units => debug!(target: "", "{e}"),
+            Err(CubteraError::Skipped(e)) => {
+                warn!(target: "", "{e}");
+                skipped.push(UnitRun {
+                    name,
+                    status: UnitRunStatus::Skipped("not allowed".into()),
+                    duration: Duration::ZERO,
+                });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    if units.is_empty() && skipped.is_empty() {
+        return Err(CubteraError::Unit(format!(
+            "No units found for dimensions {dimensions:?}"
+        )));
+    }
+
+    let graph = UnitGraph::new(&units)?;
+    // destroy goes from dependent units to their dependencies
+    let reverse =
+        command.first().is_some_and(|c| c == "destroy") || command.iter().any(|c| c == "-destroy");
+    info!(target: "", "Units order: {}", graph.order(reverse).join(" -> ").blue());
+
+    let mut results = graph.execute(reverse, parallelism, policy, |unit| {
+        info!(target: "", "Running unit {}", unit.green());
+        batch::run_unit_process(
+            unit,
+            &dimensions,
+            &extensions,
+            context.as_ref(),
+            &command,
+            drift_exit_codes[unit],
+        )
+    });
+    results.extend(skipped);
+
+    batch::report("UNIT", &results)
+}
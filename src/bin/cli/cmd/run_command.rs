@@ -1,6 +1,9 @@
+use super::batch;
 use clap::{Arg, ArgAction, ArgMatches, Command};
 use cubtera::core::dim::data::Storage;
+use cubtera::core::unit::graph::{FailurePolicy, UnitGraph, UnitRun, UnitRunStatus};
 use cubtera::prelude::*;
+use std::time::Duration;
 
 pub fn get_command() -> Command {
     Command::new("run")
@@ -15,7 +18,7 @@ pub fn get_command() -> Command {
                 .value_name("dim_type:dim_name")
                 .number_of_values(1)
                 .value_parser(super::if_contains(":"))
-                .required(true),
+                .required_unless_present("matrix"),
         )
         .arg(
             Arg::new("ext")
@@ -46,6 +49,25 @@ pub fn get_command() -> Command {
                 .short('c')
                 .long("context")
         )
+        .arg(
+            Arg::new("matrix")
+                .help("Dimension type (opt) to run the unit with every dimension of this type")
+                .long("matrix")
+                .value_name("dim_type")
+                .required(false),
+        )
+        .arg(
+            Arg::new("where")
+                .action(ArgAction::Append)
+                .help("Parent dimension (opt) of matrix dimensions, e.g. env:prod")
+                .long("where")
+                .value_name("dim_type:dim_name")
+                .number_of_values(1)
+                .value_parser(super::if_contains(":"))
+                .requires("matrix"),
+        )
+        .arg(batch::get_parallelism_arg().requires("matrix"))
+        .arg(batch::get_on_failure_arg().requires("matrix"))
         .arg(
             Arg::new("command")
                 .last(true)
@@ -57,7 +79,11 @@ pub fn get_command() -> Command {
 }
 
 #[allow(clippy::needless_pass_by_value)]
-pub fn run(sub_matches: &ArgMatches, storage: &Storage) {
+pub fn run(sub_matches: &ArgMatches, storage: &Storage) -> CubteraResult<()> {
+    if sub_matches.contains_id("matrix") {
+        return run_matrix(sub_matches, storage);
+    }
+
     let dimensions = sub_matches
         .get_many::<String>("dim")
         .unwrap()
@@ -83,14 +109,110 @@ pub fn run(sub_matches: &ArgMatches, storage: &Storage) {
         .collect::<Vec<String>>();
 
     let extensions = extensions.as_slice();
-    let unit = Unit::new(unit_name, dimensions, extensions, storage, context).build();
+    let unit = Unit::new(unit_name, dimensions, extensions, storage, context)?.build()?;
 
     let res = RunnerBuilder::new(unit, command)
-        .build()
+        .build()?
         .run()
-        .unwrap_or_exit("Unit runner failed".to_string());
+        .or_error(CubteraError::Runner, "Unit runner failed".to_string())?;
 
     let exit_code = res["exit_code"].as_i64().unwrap_or(0);
     #[allow(clippy::cast_possible_truncation)]
     std::process::exit(exit_code as i32);
 }
+
+// Runs the unit with every dimension of the matrix type, under all --where parents if set
+fn run_matrix(sub_matches: &ArgMatches, storage: &Storage) -> CubteraResult<()> {
+    use yansi::Paint; // don't move this to the top - conflicts with a crate
+
+    let get_list = |id: &str| {
+        sub_matches
+            .get_many::<String>(id)
+            .unwrap_or_default()
+            .map(std::string::ToString::to_string)
+            .collect::<Vec<String>>()
+    };
+    let dimensions = get_list("dim");
+    let extensions = get_list("ext");
+    let command = get_list("command");
+    let parents = get_list("where");
+    let unit_name = sub_matches.get_one::<String>("unit").unwrap().clone();
+    let matrix_type = sub_matches.get_one::<String>("matrix").unwrap().clone();
+    let context = sub_matches.get_one::<String>("context").cloned();
+    let parallelism = *sub_matches.get_one::<usize>("parallelism").unwrap();
+    let policy = FailurePolicy::str_to_policy(sub_matches.get_one::<String>("on_failure").unwrap());
+
+    if dimensions.iter().any(|dim| dim.starts_with(&format!("{matrix_type}:"))) {
+        return Err(CubteraError::Unit(format!(
+            "Dimension of matrix type [{matrix_type}] can't be provided with -d"
+        )));
+    }
+
+    // all names of the type, or kids of every --where parent
+    let mut names = DimBuilder::new(&matrix_type, &GLOBAL_CFG.org, storage)
+        .with_context(context.clone())
+        .get_all_dim_names()?;
+    for parent in &parents {
+        let (parent_type, parent_name) = parent.split_once(':').unwrap_or_default();
+        let kids = DimBuilder::new(parent_type, &GLOBAL_CFG.org, storage)
+            .with_name(parent_name)
+            .with_context(context.clone())
+            .get_all_kids_by_type(&matrix_type)?;
+        names.retain(|name| kids.contains(name));
+    }
+    names.sort();
+    if names.is_empty() {
+        return Err(CubteraError::Dim(format!(
+            "No [{matrix_type}] dimensions found for matrix run with parents {parents:?}"
+        )));
+    }
+
+    // every run is checked before the start, not allowed ones are skipped
+    let mut runs = Vec::new();
+    let mut skipped = Vec::new();
+    let mut drift_exit_codes = std::collections::HashMap::new();
+    for name in names {
+        let matrix_dim = format!("{matrix_type}:{name}");
+        let mut run_dims = dimensions.clone();
+        run_dims.push(matrix_dim.clone());
+        match Unit::new(unit_name.clone(), &run_dims, &extensions, storage, context.clone())
+            .and_then(Unit::build)
+        {
+            Ok(unit) => {
+                drift_exit_codes.insert(
+                    matrix_dim.clone(),
+                    RunnerBuilder::new(unit, command.clone()).get_drift_exit_code()?,
+                );
+                runs.push((matrix_dim, vec![]));
+            }
+            Err(CubteraError::Skipped(e)) => {
+                debug!(target: "", "{e}");
+                skipped.push(UnitRun {
+                    name: matrix_dim,
+                    status: UnitRunStatus::Skipped("not allowed".into()),
+                    duration: Duration::ZERO,
+                });
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    info!(target: "", "Running unit {} with {} [{}] dimensions", unit_name.green(), runs.len(), matrix_type.blue());
+
+    // matrix runs are independent, graph without dependencies is used for parallel execution
+    let mut results = UnitGraph::new(&runs)?.execute(false, parallelism, policy, |matrix_dim| {
+        info!(target: "", "Running unit {} with {}", unit_name.green(), matrix_dim.blue());
+        let mut run_dims = dimensions.clone();
+        run_dims.push(matrix_dim.to_string());
+        batch::run_unit_process(
+            &unit_name,
+            &run_dims,
+            &extensions,
+            context.as_ref(),
+            &command,
+            drift_exit_codes[matrix_dim],
+        )
+    });
+    results.extend(skipped);
+
+    batch::report("DIMENSION", &results)
+}
@@ -52,7 +52,7 @@ pub struct CubteraConfig {
     #[serde(default)]
     pub always_copy_files: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub runner: Option<HashMap<String, HashMap<String, String>>>,
+    pub runner: Option<HashMap<String, HashMap<String, Value>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub state: Option<HashMap<String, HashMap<String, String>>>,
     #[serde(skip)]
@@ -240,7 +240,7 @@ impl CubteraConfig {
         cfg.inventory_path = cfg.define_path(config.clone(), "inventory");
 
         if let Some(db) = cfg.db.clone() {
-            cfg.db_client = Some(db_connect(&db));
+            cfg.db_client = Some(db_connect(&db).unwrap_or_exit("Invalid db config".into()));
         }
 
         cfg
@@ -274,7 +274,7 @@ impl CubteraConfig {
         self.db_client.clone()
     }
 
-    pub fn get_runner_by_type(&self, runner_type: &str) -> Option<HashMap<String, String>> {
+    pub fn get_runner_by_type(&self, runner_type: &str) -> Option<HashMap<String, Value>> {
         self.runner
             .clone()
             .and_then(|r| r.get(runner_type).cloned())
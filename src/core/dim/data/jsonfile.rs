@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use super::DataSource;
+use super::{DataSource, OUTPUTS_KEY};
 use crate::prelude::*;
 use serde_json::{json, Value};
 use std::{collections::HashMap, path::PathBuf};
@@ -32,8 +32,8 @@ impl DataSource for JsonDataSource {
             filter.replace_range(0..1, ".")
         };
 
-        let mut data = std::fs::read_dir(&self.path)
-            .unwrap_or_exit(format!("Can't read data folder: {:?}", self.path))
+        let data = std::fs::read_dir(&self.path)
+            .or_error(CubteraError::DataSource, format!("Can't read data folder: {:?}", self.path))?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|entry| entry.is_file())
@@ -58,13 +58,28 @@ impl DataSource for JsonDataSource {
                                 // or return the name without the filter: <name>:manifest.json -> manifest
                                 .unwrap_or(file_name.trim_start_matches(&filter))
                                 .to_string(),
-                            read_json_file(&file).unwrap_or_exit(format!(
-                                "Failed to parse data from json file: {file:?}"
-                            )),
+                            file.clone(),
                         )
                     })
             })
-            .collect::<HashMap<String, Value>>();
+            .map(|(key, file)| {
+                read_json_file(&file)?
+                    .or_error(CubteraError::DataSource, format!("Failed to read data from json file: {file:?}"))
+                    .map(|value| (key, value))
+            })
+            .collect::<CubteraResult<HashMap<String, Value>>>()?;
+        // <name>:outputs.<unit>.json files are combined to one outputs object, same as DB sub-document
+        let outputs_prefix = format!("{OUTPUTS_KEY}.");
+        let (outputs, mut data): (HashMap<String, Value>, HashMap<String, Value>) = data
+            .into_iter()
+            .partition(|(key, _)| key.starts_with(&outputs_prefix));
+        let outputs = outputs
+            .into_iter()
+            .map(|(key, value)| (key.trim_start_matches(&outputs_prefix).to_string(), value))
+            .collect::<serde_json::Map<String, Value>>();
+        if !outputs.is_empty() {
+            data.insert(OUTPUTS_KEY.into(), Value::Object(outputs));
+        }
         data.insert("name".into(), json!(name));
         // dbg!(data.clone());
         Ok(json!(data))
@@ -74,11 +89,10 @@ impl DataSource for JsonDataSource {
     // and return the data for each dimension name
     fn get_all_data(&self) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
         let data = self
-            .get_all_names()
-            .unwrap_or_exit(format!("Can't read data folder: {:?}", self.path))
+            .get_all_names()?
             .iter()
-            .map(|dim_name| self.get_data_by_name(dim_name).unwrap_or_default())
-            .collect::<Vec<Value>>();
+            .map(|dim_name| self.get_data_by_name(dim_name))
+            .collect::<Result<Vec<Value>, _>>()?;
 
         Ok(data)
     }
@@ -88,7 +102,7 @@ impl DataSource for JsonDataSource {
     fn get_all_names(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let meta_suffix = format!("{}meta", &GLOBAL_CFG.file_name_separator);
         let names = std::fs::read_dir(&self.path)
-            .unwrap_or_exit(format!("Can't read data folder: {:?}", self.path))
+            .or_error(CubteraError::DataSource, format!("Can't read data folder: {:?}", self.path))?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|entry| entry.is_file())
@@ -108,7 +122,7 @@ impl DataSource for JsonDataSource {
 
     fn get_all_types(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
         let types = std::fs::read_dir(self.path.parent().unwrap_or(self.path.as_path()))
-            .unwrap_or_exit(format!("Can't read data folder: {:?}", self.path))
+            .or_error(CubteraError::DataSource, format!("Can't read data folder: {:?}", self.path))?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|path| path.is_dir())
@@ -118,6 +132,23 @@ impl DataSource for JsonDataSource {
         Ok(types)
     }
 
+    // writes unit outputs to <name>:outputs.<unit_name>.json
+    fn upsert_outputs_by_name(
+        &self,
+        name: &str,
+        unit_name: &str,
+        outputs: Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let file_name = format!(
+            "{}{}{}.{}.json",
+            name, &GLOBAL_CFG.file_name_separator, OUTPUTS_KEY, unit_name
+        );
+        let path = self.path.join(file_name);
+        std::fs::write(&path, serde_json::to_string_pretty(&outputs)? + "\n")
+            .or_error(CubteraError::DataSource, format!("Can't write outputs file: {path:?}"))?;
+        Ok(())
+    }
+
     fn set_context(&mut self, context: Option<String>) {
         self.context = context;
     }
@@ -195,6 +226,34 @@ mod tests {
         assert!(meta.contains(&serde_json::from_str::<Value>(json_content2).unwrap()));
     }
 
+    #[test]
+    fn test_upsert_outputs_by_name() {
+        let dir = tempdir().unwrap();
+        let org = "cubtera";
+        let dim_type = "dc";
+        let name = "stg1-use1";
+
+        let dim_path = dir.path().join(org).join(dim_type);
+        fs::create_dir_all(&dim_path).unwrap();
+        create_test_file(&dim_path, &format!("{}:meta.json", name), r#"{ "region": "us-east-1" }"#);
+
+        let data_source = JsonDataSource::new(org, dim_type, dir.path().to_str().unwrap());
+        data_source
+            .upsert_outputs_by_name(name, "network", json!({ "vpc_id": "vpc-1" }))
+            .unwrap();
+        data_source
+            .upsert_outputs_by_name(name, "dns", json!({ "zone_id": "Z1" }))
+            .unwrap();
+        assert!(dim_path.join(format!("{}:outputs.network.json", name)).exists());
+
+        let result = data_source.get_data_by_name(name).unwrap();
+        assert_eq!(result["outputs"]["network"]["vpc_id"], "vpc-1");
+        assert_eq!(result["outputs"]["dns"]["zone_id"], "Z1");
+        assert_eq!(result["meta"]["region"], "us-east-1");
+        assert!(result.get("outputs.network").is_none());
+        assert_eq!(data_source.get_all_names().unwrap(), [name]);
+    }
+
     #[test]
     fn test_get_all_names() {
         let dir = tempdir().unwrap();
@@ -1,9 +1,13 @@
 mod jsonfile;
 mod mongodb;
 
+use crate::core::error::{CubteraError, CubteraResult};
 use crate::globals::GLOBAL_CFG;
 use serde_json::Value;
 
+// Dim data key with units outputs: { "<unit_name>": { "<output>": <value> } }
+pub const OUTPUTS_KEY: &str = "outputs";
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Storage {
     FS,
@@ -84,6 +88,21 @@ pub trait DataSource: CloneBox + 'static {
         Ok(())
     }
 
+    // replaces unit outputs in dim data, other units outputs are kept
+    fn upsert_outputs_by_name(
+        &self,
+        name: &str,
+        unit_name: &str,
+        _outputs: Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        log::debug!(
+            "this data source doesn't support upsert_outputs_by_name: {}: {}",
+            name,
+            unit_name
+        );
+        Ok(())
+    }
+
     fn delete_all_by_context(&self, context: &str) -> Result<(), Box<dyn std::error::Error>> {
         log::debug!(
             "this data source doesn't support delete_all_by_context: {}",
@@ -96,15 +115,77 @@ pub trait DataSource: CloneBox + 'static {
     fn get_context(&self) -> Option<String>;
 }
 
-pub fn data_src_init(org: &str, dim_type: &str, storage: Storage) -> Box<dyn DataSource> {
-    match storage {
-        Storage::DB => Box::new(mongodb::MongoDBDataSource::new(org, dim_type)),
+pub fn data_src_init(
+    org: &str,
+    dim_type: &str,
+    storage: Storage,
+) -> CubteraResult<Box<dyn DataSource>> {
+    Ok(match storage {
+        Storage::DB => Box::new(mongodb::MongoDBDataSource::new(org, dim_type)?),
         Storage::FS => Box::new(jsonfile::JsonDataSource::new(
             org,
             dim_type,
             &GLOBAL_CFG.inventory_path,
         )),
         //_ => unreachable!("Unknown storage type")
+    })
+}
+
+// Placeholder for a data source which failed to initialize (e.g. DB is not configured).
+// Every data call returns the init error, so builders stay infallible
+// and the error is reported by the first data access.
+#[derive(Clone)]
+pub(crate) struct UnavailableDataSource {
+    error: String,
+    context: Option<String>,
+}
+
+impl UnavailableDataSource {
+    pub(crate) fn new_boxed(error: CubteraError) -> Box<dyn DataSource> {
+        Box::new(Self {
+            error: error.to_string(),
+            context: None,
+        })
+    }
+
+    fn err<T>(&self) -> Result<T, Box<dyn std::error::Error>> {
+        Err(CubteraError::DataSource(self.error.clone()).into())
+    }
+}
+
+impl DataSource for UnavailableDataSource {
+    fn get_data_by_name(&self, _: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn get_all_data(&self) -> Result<Vec<Value>, Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn get_all_names(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn get_all_types(&self) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn upsert_all_data(&self, _: Vec<Value>) -> Result<(), Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn upsert_data_by_name(&self, _: &str, _: Value) -> Result<(), Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn delete_data_by_name(&self, _: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn upsert_outputs_by_name(&self, _: &str, _: &str, _: Value) -> Result<(), Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn delete_all_by_context(&self, _: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.err()
+    }
+    fn set_context(&mut self, context: Option<String>) {
+        self.context = context;
+    }
+    fn get_context(&self) -> Option<String> {
+        self.context.clone()
     }
 }
 
@@ -1,5 +1,5 @@
 #![allow(dead_code)]
-use super::DataSource;
+use super::{DataSource, OUTPUTS_KEY};
 use crate::prelude::*;
 use mongodb::bson::{doc, Bson};
 use mongodb::sync::Client;
@@ -20,23 +20,23 @@ pub struct MongoDBDataSource {
 }
 
 impl MongoDBDataSource {
-    pub fn new(org: &str, dim_type: &str) -> Self {
+    pub fn new(org: &str, dim_type: &str) -> CubteraResult<Self> {
         match GLOBAL_CFG.db_client.as_ref() {
             Some(client) => {
                 let db_name = org.to_string();
                 let col_name = dim_type.to_string();
                 let db = client.database(&db_name);
                 let col = db.collection::<Bson>(&col_name);
-                Self {
+                Ok(Self {
                     client: client.clone(),
                     col,
                     db,
                     db_name,
                     col_name,
                     context: None,
-                }
+                })
             }
-            None => exit_with_error("No DB config found".to_string()),
+            None => Err(CubteraError::DataSource("No DB config found".into())),
         }
     }
 }
@@ -79,7 +79,8 @@ impl DataSource for MongoDBDataSource {
         };
         let bson = self.col.find(filter).run()?;
         let res = bson
-            .map(|doc| doc.unwrap())
+            .collect::<Result<Vec<Bson>, _>>()?
+            .into_iter()
             .map(|doc| mongodb::bson::from_bson::<Value>(doc).unwrap_or_default())
             .collect::<Vec<Value>>();
         Ok(res)
@@ -154,6 +155,31 @@ impl DataSource for MongoDBDataSource {
         Ok(())
     }
 
+    // sets outputs.<unit_name> field of existing dim document
+    fn upsert_outputs_by_name(
+        &self,
+        name: &str,
+        unit_name: &str,
+        outputs: Value,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut query = doc! { "name": name, "context": { "$exists": false } };
+        if let Some(ctx) = &self.context {
+            if !ctx.is_empty() {
+                query = doc! { "name": name, "context": ctx };
+            }
+        }
+        let update = doc! { "$set": { format!("{OUTPUTS_KEY}.{unit_name}"): mongodb::bson::to_bson(&outputs)? } };
+        let res = self.col.update_one(query, update).run()?;
+        if res.matched_count == 0 {
+            return Err(CubteraError::DataSource(format!(
+                "Dimension {}:{} is not found in DB",
+                self.col_name, name
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
     fn delete_data_by_name(&self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
         let mut filter = doc! { "name": name, "context": { "$exists": false } };
         if let Some(ctx) = &self.context {
@@ -166,15 +192,11 @@ impl DataSource for MongoDBDataSource {
     }
 
     fn delete_all_by_context(&self, context: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let db_names = &GLOBAL_CFG
-            .db_client
-            .as_ref()
-            .unwrap()
-            .list_database_names().run()?;
+        let db_names = &self.client.list_database_names().run()?;
         db_names.iter()
-            .for_each(|name| {
-                let db = GLOBAL_CFG.db_client.as_ref().unwrap().database(name);
-                db.list_collection_names().run().unwrap()
+            .try_for_each(|name| {
+                let db = self.client.database(name);
+                db.list_collection_names().run()?
                     .iter()
                     .for_each(|col| {
                         let col = db.collection::<Bson>(col);
@@ -187,7 +209,8 @@ impl DataSource for MongoDBDataSource {
                             }
                         }
                     });
-            });
+                Ok::<(), mongodb::error::Error>(())
+            })?;
         Ok(())
     }
 
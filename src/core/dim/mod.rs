@@ -60,7 +60,11 @@ impl Dim {
 
     // Save dimension variables values to json file
     pub fn save_json_dim_vars(&self, path: PathBuf) -> Result<String, std::io::Error> {
-        let json_content = self.get_json_dim_vars();
+        let mut json_content = self.get_json_dim_vars();
+        // dim data is collected from hash maps, sorted keys keep generated files stable between runs
+        if let Some(obj) = json_content.as_object_mut() {
+            obj.sort_keys();
+        }
         let json_vars_file_name = format!("cubtera_dim_{}.json", &self.dim_type);
         let json_vars_file_path = path.join(&json_vars_file_name);
         std::fs::write(
@@ -122,8 +126,7 @@ impl Dim {
 
     fn copy_entry(&self, src: &Path, dest: &Path, is_dir: bool) -> std::io::Result<()> {
         if is_dir {
-            copy_folder(src.to_path_buf(), &dest.to_path_buf(), true);
-            Ok(())
+            copy_folder(src.to_path_buf(), &dest.to_path_buf(), true)
         } else {
             std::fs::copy(src, dest).map(|_| ())
         }
@@ -169,7 +172,8 @@ impl Default for DimBuilder {
             dim_type: String::new(),
             org: String::new(),
             dim_path: PathBuf::new(),
-            datasource: data_src_init("", "", Storage::FS),
+            datasource: data_src_init("", "", Storage::FS)
+                .unwrap_or_else(UnavailableDataSource::new_boxed),
             storage: Storage::FS,
             data: Value::Null,
             default_data: Value::Null,
@@ -180,12 +184,14 @@ impl Default for DimBuilder {
 impl DimBuilder {
     pub fn switch_datasource(mut self, storage: &Storage) -> Self {
         self.storage = storage.clone();
-        self.datasource = data_src_init(&self.org, &self.dim_type, storage.clone());
+        self.datasource = data_src_init(&self.org, &self.dim_type, storage.clone())
+            .unwrap_or_else(UnavailableDataSource::new_boxed);
         self
     }
 
     pub fn new(dim_type: &str, org: &str, storage: &Storage) -> Self {
-        let datasource = data_src_init(org, dim_type, storage.clone());
+        let datasource = data_src_init(org, dim_type, storage.clone())
+            .unwrap_or_else(UnavailableDataSource::new_boxed);
         Self {
             dim_path: Path::new(&GLOBAL_CFG.inventory_path)
                 .join(org)
@@ -198,8 +204,13 @@ impl DimBuilder {
         }
     }
 
-    pub fn new_from_cli(dim: &str, org: &str, storage: &Storage, context: Option<String>) -> Dim {
-        let (dim_type, dim_name) = Self::split_by_colon(dim);
+    pub fn new_from_cli(
+        dim: &str,
+        org: &str,
+        storage: &Storage,
+        context: Option<String>,
+    ) -> CubteraResult<Dim> {
+        let (dim_type, dim_name) = Self::split_by_colon(dim)?;
         Self::new(&dim_type, org, storage)
             .with_name(&dim_name)
             .with_context(context)
@@ -244,17 +255,20 @@ impl DimBuilder {
         self
     }
 
-    pub fn get_all_dim_data(&self) -> Vec<Value> {
-        self.datasource.get_all_data().unwrap_or_default()
+    pub fn get_all_dim_data(&self) -> CubteraResult<Vec<Value>> {
+        self.datasource.get_all_data().or_error(
+            CubteraError::DataSource,
+            format!("Can't read {} dimensions data", &self.dim_type),
+        )
     }
 
-    pub fn get_all_kids_by_name(&self) -> HashMap<String, Vec<String>> {
+    pub fn get_all_kids_by_name(&self) -> CubteraResult<HashMap<String, Vec<String>>> {
         if GLOBAL_CFG.dim_relations.is_empty() {
             warn!(
                 "No dim_relations found in config for {}:{}",
                 &self.dim_type, &self.dim_name
             );
-            return HashMap::new();
+            return Ok(HashMap::new());
         }
         let child_index = GLOBAL_CFG
             .dim_relations
@@ -264,12 +278,12 @@ impl DimBuilder {
             .unwrap_or_default();
         if child_index >= GLOBAL_CFG.dim_relations.len() || child_index == 0 {
             debug!("No child dim found for {}", &self.dim_type);
-            return HashMap::new();
+            return Ok(HashMap::new());
         }
         let child_dim_type = &GLOBAL_CFG.dim_relations[child_index];
         let data = DimBuilder::new(child_dim_type, &self.org, &self.storage)
             .with_context(self.datasource.get_context())
-            .get_all_dim_data()
+            .get_all_dim_data()?
             .into_iter()
             .filter(|data| data["name"].is_string())
             .filter(|data| {
@@ -281,7 +295,32 @@ impl DimBuilder {
 
         let mut kids: HashMap<String, Vec<String>> = HashMap::new();
         kids.insert(child_dim_type.into(), data);
-        kids
+        Ok(kids)
+    }
+
+    // Names of dim_type dimensions under this one in dim_relations tree, e.g. all dc of dome:prod
+    pub fn get_all_kids_by_type(&self, dim_type: &str) -> CubteraResult<Vec<String>> {
+        let position = |t: &str| GLOBAL_CFG.dim_relations.iter().position(|r| r == t);
+        if position(dim_type) <= position(&self.dim_type) {
+            return Ok(Vec::new());
+        }
+
+        let mut names = Vec::new();
+        for (kid_type, kid_names) in self.get_all_kids_by_name()? {
+            for kid_name in kid_names {
+                if kid_type == dim_type {
+                    names.push(kid_name);
+                    continue;
+                }
+                names.extend(
+                    DimBuilder::new(&kid_type, &self.org, &self.storage)
+                        .with_name(&kid_name)
+                        .with_context(self.datasource.get_context())
+                        .get_all_kids_by_type(dim_type)?,
+                );
+            }
+        }
+        Ok(names)
     }
 
     pub fn merge_defaults(mut self) -> Self {
@@ -291,41 +330,45 @@ impl DimBuilder {
         self
     }
 
-    pub fn get_all_dim_names(&self) -> Vec<String> {
-        self.datasource.get_all_names().unwrap_or_default()
+    pub fn get_all_dim_names(&self) -> CubteraResult<Vec<String>> {
+        self.datasource.get_all_names().or_error(
+            CubteraError::DataSource,
+            format!("Can't read {} dimension names", &self.dim_type),
+        )
     }
 
-    pub fn full_build(self) -> Dim {
-        self.read_data()
+    pub fn full_build(self) -> CubteraResult<Dim> {
+        self.read_data()?
             .read_default_data()
             .merge_defaults()
             .build()
     }
 
-    pub fn build(mut self) -> Dim {
+    pub fn build(mut self) -> CubteraResult<Dim> {
         // ------------------ parent (optional) ------------------
         let parent = match self.data["meta"].get("parent") {
             Some(parent) => {
-                let parent = parent
-                    .as_str()
-                    .unwrap_or_exit(format!("Parent should be a string. Got: {parent}"));
+                let parent = parent.as_str().or_error(
+                    CubteraError::Dim,
+                    format!("Parent should be a string. Got: {parent}"),
+                )?;
                 if parent.find(':').is_none() {
-                    exit_with_error(format!(
+                    return Err(CubteraError::Dim(format!(
                         "Parent must be in format <parent_dim_type>:<parent_dim_name>. Got: {parent}"
-                    ))
+                    )));
                 }
-                let (parent_type, parent_name) = Self::split_by_colon(parent);
+                let (parent_type, parent_name) = Self::split_by_colon(parent)?;
                 let parent_dim = DimBuilder::new(&parent_type, &self.org, &self.storage)
                     .with_name(&parent_name)
                     .with_context(self.datasource.get_context())
-                    .full_build();
+                    .full_build()?;
                 Some(Box::new(parent_dim))
             }
             None => None,
         };
 
         let kids: Vec<String> = self
-            .get_all_kids_by_name()
+            .get_all_kids_by_name()?
             .into_iter()
             .flat_map(|(k, v)| v.into_iter().map(move |x| format!("{}:{}", k, x)))
             .collect();
@@ -343,7 +386,7 @@ impl DimBuilder {
 
         let data_sha = get_sha_by_value(&self.data);
 
-        Dim {
+        Ok(Dim {
             dim_name: self.dim_name,
             dim_type: self.dim_type,
             dim_path: self.dim_path,
@@ -352,7 +395,7 @@ impl DimBuilder {
             key_path,
             parent,
             kids: kids.is_empty().not().then(|| kids),
-        }
+        })
     }
 
     // --------------------- data ---------------------
@@ -360,38 +403,57 @@ impl DimBuilder {
         self.data.clone()
     }
 
-    pub fn read_data(mut self) -> Self {
+    pub fn read_data(mut self) -> CubteraResult<Self> {
         let data = self
             .datasource
             .get_data_by_name(&self.dim_name)
-            .unwrap_or_default();
-        data.get("meta").is_none().then(|| match self.storage {
-            Storage::FS => exit_with_error(format!(
-                "Can't find meta data for dimension {}:{}",
-                self.dim_type, self.dim_name
-            )),
-            Storage::DB => {
-                warn!(target: "",
-                    "Can't find meta data for dimension {}:{}",
-                    self.dim_type, self.dim_name
-                )
+            .or_error(
+                CubteraError::DataSource,
+                format!("Can't read dimension {}:{}", self.dim_type, self.dim_name),
+            )?;
+        if data.get("meta").is_none() {
+            match self.storage {
+                Storage::FS => {
+                    return Err(CubteraError::Dim(format!(
+                        "Can't find meta data for dimension {}:{}",
+                        self.dim_type, self.dim_name
+                    )))
+                }
+                Storage::DB => {
+                    warn!(target: "",
+                        "Can't find meta data for dimension {}:{}",
+                        self.dim_type, self.dim_name
+                    )
+                }
             }
-        });
-        self.data = data.clone();
-        self
+        }
+        self.data = data;
+        Ok(self)
     }
 
-    pub fn save_data(&self) {
+    pub fn save_data(&self) -> CubteraResult<()> {
         let mut data = self.data.clone();
         data["name"] = json!(self.dim_name);
         self.datasource
             .upsert_data_by_name(&self.dim_name, data)
-            .unwrap_or_exit(format!("Error saving dim {} data to DB:", &self.dim_name));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error saving dim {} data to DB", &self.dim_name),
+            )
+    }
+
+    pub fn save_outputs(&self, unit_name: &str, outputs: Value) -> CubteraResult<()> {
+        self.datasource
+            .upsert_outputs_by_name(&self.dim_name, unit_name, outputs)
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error saving unit {unit_name} outputs to dim {}:{}", &self.dim_type, &self.dim_name),
+            )
     }
 
-    pub fn save_all_data_by_type(&self) {
+    pub fn save_all_data_by_type(&self) -> CubteraResult<()> {
         use yansi::Paint;
-        let data = self.get_all_dim_data();
+        let data = self.get_all_dim_data()?;
         let count = data.clone().len();
 
         let builder = DimBuilder::new(&self.dim_type, &self.org, &Storage::DB)
@@ -399,20 +461,21 @@ impl DimBuilder {
         builder
             .datasource
             .upsert_all_data(data)
-            .unwrap_or_exit(format!(
-                "Error saving dim {} data to DB:",
-                &self.dim_type.red()
-            ));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error saving dim {} data to DB", &self.dim_type.red()),
+            )?;
         info!(target: "im", "Saved {} dimensions of {} type", count.blue(), &self.dim_type.blue());
+        Ok(())
     }
 
-    pub fn delete_data(&self) {
+    pub fn delete_data(&self) -> CubteraResult<()> {
         self.datasource
             .delete_data_by_name(&self.dim_name)
-            .unwrap_or_exit(format!(
-                "Error deleting dim {} data from DB:",
-                &self.dim_name
-            ));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error deleting dim {} data from DB", &self.dim_name),
+            )
     }
 
     // ------------------ default data ------------------
@@ -437,7 +500,7 @@ impl DimBuilder {
         self
     }
 
-    pub fn save_default_data(&self) {
+    pub fn save_default_data(&self) -> CubteraResult<()> {
         let data = self.default_data.clone();
 
         // TODO: remove "data" key usage and save default data as is
@@ -447,45 +510,45 @@ impl DimBuilder {
 
         self.datasource
             .upsert_data_by_name("_default", data)
-            .unwrap_or_exit(format!(
-                "Error saving default data {} to DB",
-                &self.dim_type
-            ));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error saving default data {} to DB", &self.dim_type),
+            )
     }
 
-    pub fn delete_default_data(&self) {
+    pub fn delete_default_data(&self) -> CubteraResult<()> {
         self.datasource
             .delete_data_by_name("_default")
-            .unwrap_or_exit(format!(
-                "Error deleting default data {} from DB:",
-                &self.dim_type
-            ));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error deleting default data {} from DB", &self.dim_type),
+            )
     }
 
-    pub fn delete_all_data_by_context(&self) {
+    pub fn delete_all_data_by_context(&self) -> CubteraResult<()> {
         if let Some(context) = self.datasource.get_context() {
             self.datasource
                 .delete_all_by_context(&context)
-                .unwrap_or_exit(format!(
-                    "Error deleting all data by context {} from DB:",
-                    &self.dim_type
-                ));
+                .or_error(
+                    CubteraError::DataSource,
+                    format!("Error deleting all data by context {} from DB", &self.dim_type),
+                )?;
         }
         self.datasource
             .delete_all_by_context(&self.datasource.get_context().unwrap_or_default())
-            .unwrap_or_exit(format!(
-                "Error deleting all data by context {} from DB:",
-                &self.dim_type
-            ));
+            .or_error(
+                CubteraError::DataSource,
+                format!("Error deleting all data by context {} from DB", &self.dim_type),
+            )
     }
 
     // helper methods related to DimBuilder
-    fn split_by_colon(dim: &str) -> (String, String) {
+    fn split_by_colon(dim: &str) -> CubteraResult<(String, String)> {
         match dim.split_once(':') {
-            Some((dim_type, dim_name)) => (dim_type.to_string(), dim_name.to_string()),
-            None => exit_with_error(format!(
+            Some((dim_type, dim_name)) => Ok((dim_type.to_string(), dim_name.to_string())),
+            None => Err(CubteraError::Dim(format!(
                 "Dim name must be in format <dim_type>:<dim_name>. Got: {dim}"
-            )),
+            ))),
         }
     }
 }
@@ -522,7 +585,7 @@ mod tests {
     #[test]
     fn split_by_colon_with_valid_input() {
         let input = "type:name";
-        let (dim_type, dim_name) = DimBuilder::split_by_colon(input);
+        let (dim_type, dim_name) = DimBuilder::split_by_colon(input).unwrap();
         assert_eq!(dim_type, "type");
         assert_eq!(dim_name, "name");
     }
@@ -530,25 +593,42 @@ mod tests {
     #[test]
     fn split_by_colon_with_multiple_colons() {
         let input = "type:name:extra";
-        let (dim_type, dim_name) = DimBuilder::split_by_colon(input);
+        let (dim_type, dim_name) = DimBuilder::split_by_colon(input).unwrap();
         assert_eq!(dim_type, "type");
         assert_eq!(dim_name, "name:extra");
     }
 
-    // TODO: unwind can't handle exit(1) cases. Fix.
-    // #[test]
-    // fn split_by_colon_with_empty_string() {
-    //     let input = "";
-    //     let result = std::panic::catch_unwind(|| DimBuilder::split_by_colon(input));
-    //     //assert!(result.is_err());
-    //     assert_eq!(format!("{:?}", result), "Dim name must be in format <dim_type>:<dim_name>. Got: ");
-    // }
-    // #[test]
-    // fn split_by_colon_with_missing_colon() {
-    //     let input = "typename";
-    //     let result = std::panic::catch_unwind(|| DimBuilder::split_by_colon(input));
-    //     assert!(result.is_err());
-    // }
+    #[test]
+    fn split_by_colon_with_empty_string() {
+        let input = "";
+        let result = DimBuilder::split_by_colon(input);
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Dim name must be in format <dim_type>:<dim_name>. Got: "
+        );
+    }
+
+    #[test]
+    fn split_by_colon_with_missing_colon() {
+        let input = "typename";
+        let result = DimBuilder::split_by_colon(input);
+        assert!(matches!(result, Err(CubteraError::Dim(_))));
+    }
+
+    #[test]
+    fn build_with_invalid_parent() {
+        let mut builder = DimBuilder::new("test_type", "test_org", &Storage::FS).with_name("test");
+        builder.data["meta"] = json!({ "parent": "no_colon" });
+        assert!(matches!(builder.build(), Err(CubteraError::Dim(_))));
+    }
+
+    #[test]
+    fn read_data_from_missing_folder() {
+        let result = DimBuilder::new("test_type", "missing_org", &Storage::FS)
+            .with_name("test")
+            .read_data();
+        assert!(matches!(result, Err(CubteraError::DataSource(_))));
+    }
 
     #[test]
     fn test_dim_tree_generation() {
@@ -664,22 +744,26 @@ mod tests {
         assert!(result.is_ok());
     }
 
-    // #[test]
-    // fn test_dim_builder_switch_datasource() {
-    //     let builder = DimBuilder::new("test_type", "test_org", &Storage::FS);
-    //     let switched = builder.switch_datasource(&Storage::DB);
-    //     assert_eq!(
-    //         format!("{:?}", switched.storage),
-    //         format!("{:?}", Storage::DB)
-    //     );
-    // }
-
-    // #[test]
-    // fn test_new_undefined_dim() {
-    //     let dim = DimBuilder::new_undefined("test_type").build();
-    //     assert_eq!(dim.dim_name, "undefined".to_string());
-    //     assert_eq!(dim.dim_type, "test_type".to_string());
-    // }
+    #[test]
+    fn test_dim_builder_switch_datasource() {
+        let builder = DimBuilder::new("test_type", "test_org", &Storage::FS);
+        let switched = builder.switch_datasource(&Storage::DB);
+        assert_eq!(
+            format!("{:?}", switched.storage),
+            format!("{:?}", Storage::DB)
+        );
+        // without DB config data access fails instead of exiting
+        if GLOBAL_CFG.db_client.is_none() {
+            assert!(switched.get_all_dim_names().is_err());
+        }
+    }
+
+    #[test]
+    fn test_new_undefined_dim() {
+        let dim = DimBuilder::new_undefined("test_type").build().unwrap();
+        assert_eq!(dim.dim_name, "undefined".to_string());
+        assert_eq!(dim.dim_type, "test_type".to_string());
+    }
 
     #[test]
     fn test_dim_builder_with_context() {
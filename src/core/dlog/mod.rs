@@ -8,6 +8,9 @@ use serde_json::Value;
 use std::collections::HashMap;
 use std::path::Path;
 
+// number of captured output lines saved to dlog
+const DLOG_OUTPUT_TAIL_LINES: usize = 50;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Dlog {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -29,6 +32,16 @@ pub struct Dlog {
     #[serde(skip_serializing_if = "Option::is_none")]
     exitcode: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    drift_status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    termination: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_log: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_tail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artifacts: Option<HashMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     unit_sha: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     unit_blob_sha: Option<String>,
@@ -101,6 +114,11 @@ impl Dlog {
             job_name: Some(job_name),
             tf_command: Some(tf_command),
             exitcode: Some(exitcode),
+            drift_status: None,
+            termination: None,
+            output_log: None,
+            output_tail: None,
+            artifacts: None,
             unit_sha: Some(unit_commit_sha),
             unit_blob_sha: Some(unit_blob_sha),
             inventory_sha: Some(inventory_commit_sha),
@@ -112,6 +130,31 @@ impl Dlog {
         }
     }
 
+    /// Sets plan drift status (`no_changes`, `changes` or `error`).
+    pub fn with_drift_status(mut self, drift_status: &str) -> Self {
+        self.drift_status = Some(drift_status.into());
+        self
+    }
+
+    /// Sets cancellation or timeout details of the run.
+    pub fn with_termination(mut self, termination: &str) -> Self {
+        self.termination = Some(termination.into());
+        self
+    }
+
+    /// Sets captured runner output log path and its last lines.
+    pub fn with_output_log(mut self, log_path: &Path) -> Self {
+        self.output_log = Some(log_path.to_string_lossy().into());
+        self.output_tail = crate::utils::output::tail(log_path, DLOG_OUTPUT_TAIL_LINES).ok();
+        self
+    }
+
+    /// Sets built artifact ids by build name (e.g. packer AMIs).
+    pub fn with_artifacts(mut self, artifacts: HashMap<String, String>) -> Self {
+        self.artifacts = Some(artifacts);
+        self
+    }
+
     /// Inserts a log entry into the MongoDB collection for the specified organization.
     ///
     /// # Arguments
@@ -123,7 +166,7 @@ impl Dlog {
     /// Returns `Ok(())` if the log entry was successfully inserted, otherwise returns an `anyhow::Error`.
     pub fn put(&self, org: &str) -> anyhow::Result<()> {
         let client: Option<mongodb::sync::Client> =
-            GLOBAL_CFG.dlog_db.as_ref().map(|db| db_connect(db));
+            GLOBAL_CFG.dlog_db.as_deref().map(db_connect).transpose()?;
         if let Some(cl) = client {
             let db = cl.database(org);
             let col = db.collection::<mongodb::bson::Bson>("dlog");
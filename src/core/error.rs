@@ -0,0 +1,112 @@
+use std::fmt::{Display, Formatter};
+
+pub type CubteraResult<T> = Result<T, CubteraError>;
+
+/// Error type of the cubtera library. Library code never exits the process,
+/// binaries decide how to report the error and which exit code to use.
+#[derive(Debug)]
+pub enum CubteraError {
+    /// Invalid dimension name, missing or malformed dimension data
+    Dim(String),
+    /// Data source (inventory files or DB) failure
+    DataSource(String),
+    /// Unit manifest, required dimensions or unit files failure
+    Unit(String),
+    /// Runner configuration or execution failure
+    Runner(String),
+    /// Unit is not allowed to run with provided dimensions (allow/deny lists, affinity tags).
+    /// Not a failure: binaries report it as a warning and exit with 0.
+    Skipped(String),
+    Io(std::io::Error),
+    Json(serde_json::Error),
+}
+
+impl CubteraError {
+    /// Process exit code the binaries should use for this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            CubteraError::Skipped(_) => 0,
+            _ => 1,
+        }
+    }
+}
+
+impl Display for CubteraError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CubteraError::Dim(e)
+            | CubteraError::DataSource(e)
+            | CubteraError::Unit(e)
+            | CubteraError::Runner(e)
+            | CubteraError::Skipped(e) => write!(f, "{e}"),
+            CubteraError::Io(e) => write!(f, "{e}"),
+            CubteraError::Json(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for CubteraError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CubteraError::Io(e) => Some(e),
+            CubteraError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CubteraError {
+    fn from(e: std::io::Error) -> Self {
+        CubteraError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for CubteraError {
+    fn from(e: serde_json::Error) -> Self {
+        CubteraError::Json(e)
+    }
+}
+
+// helper trait for mapping Result error to CubteraError with error message,
+// same message format as `unwrap_or_exit`
+pub trait ResultExtError<T> {
+    fn or_error(self, kind: fn(String) -> CubteraError, error: String) -> CubteraResult<T>;
+}
+
+impl<T, E: Display> ResultExtError<T> for Result<T, E> {
+    fn or_error(self, kind: fn(String) -> CubteraError, error: String) -> CubteraResult<T> {
+        self.map_err(|e| kind(format!("{error}: {e}")))
+    }
+}
+
+// helper trait for converting None to CubteraError with error message
+pub trait OptionExtError<T> {
+    fn or_error(self, kind: fn(String) -> CubteraError, error: String) -> CubteraResult<T>;
+}
+
+impl<T> OptionExtError<T> for Option<T> {
+    fn or_error(self, kind: fn(String) -> CubteraError, error: String) -> CubteraResult<T> {
+        self.ok_or_else(|| kind(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_or_error() {
+        let result: Result<(), &str> = Err("not found");
+        let error = result
+            .or_error(CubteraError::Dim, "Can't read dim".into())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Can't read dim: not found");
+        assert_eq!(error.exit_code(), 1);
+
+        let error = None::<()>
+            .or_error(CubteraError::Skipped, "Unit is skipped".into())
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Unit is skipped");
+        assert_eq!(error.exit_code(), 0);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::core::dim::data::*;
 use crate::core::dim::*;
+use crate::core::error::*;
 use crate::prelude::GLOBAL_CFG;
 use crate::utils::helper::*;
 
@@ -13,74 +14,83 @@ pub fn get_dim_by_name(
     org: &str,
     storage: &Storage,
     context: Option<String>,
-) -> Value {
+) -> CubteraResult<Value> {
     let dim = DimBuilder::new(dim_type, org, storage)
         .with_name(dim_name)
         .with_context(context)
-        .full_build();
+        .full_build()?;
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimByName",
         "type": dim.dim_type,
         "name": dim.dim_name,
         "data": dim.get_dim_data(),
-    })
+    }))
 }
 
-pub fn get_dim_names_by_type(dim_type: &str, org: &str, storage: &Storage) -> Value {
-    let dims = DimBuilder::new(dim_type, org, storage).get_all_dim_names();
+pub fn get_dim_names_by_type(dim_type: &str, org: &str, storage: &Storage) -> CubteraResult<Value> {
+    let dims = DimBuilder::new(dim_type, org, storage).get_all_dim_names()?;
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimsByType",
         "type": dim_type,
         "data": dims,
-    })
+    }))
 }
 
-pub fn get_dims_data_by_type(dim_type: &str, org: &str, storage: &Storage) -> Value {
-    let names = DimBuilder::new(dim_type, org, storage).get_all_dim_names();
+pub fn get_dims_data_by_type(dim_type: &str, org: &str, storage: &Storage) -> CubteraResult<Value> {
+    let names = DimBuilder::new(dim_type, org, storage).get_all_dim_names()?;
 
     let data = names
         .iter()
         .map(|name| {
             let dim = DimBuilder::new(dim_type, org, storage)
                 .with_name(name)
-                .read_data()
+                .read_data()?
                 .merge_defaults()
-                .build();
-            dim.get_dim_data()
+                .build()?;
+            Ok(dim.get_dim_data())
         })
-        .collect::<Vec<Value>>();
+        .collect::<CubteraResult<Vec<Value>>>()?;
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimsDataByType",
         "type": dim_type,
         "data": data,
-    })
+    }))
 }
 
-pub fn get_dim_defaults_by_type(dim_type: &str, org: &str, storage: &Storage) -> Value {
+pub fn get_dim_defaults_by_type(
+    dim_type: &str,
+    org: &str,
+    storage: &Storage,
+) -> CubteraResult<Value> {
     let defaults = DimBuilder::new(dim_type, org, storage)
         .read_default_data()
         .get_default_data();
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimsDefaultsByType",
         "type": dim_type,
         "data": defaults,
-    })
+    }))
 }
 
-pub fn get_dim_kids(dim_type: &str, dim_name: &str, org: &str, storage: &Storage) -> Value {
+pub fn get_dim_kids(
+    dim_type: &str,
+    dim_name: &str,
+    org: &str,
+    storage: &Storage,
+) -> CubteraResult<Value> {
     let kids = DimBuilder::new(dim_type, org, storage)
         .with_name(dim_name)
-        .get_all_kids_by_name();
+        .get_all_kids_by_name()?;
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimsByParent",
         "parent_type": dim_type,
@@ -89,16 +99,21 @@ pub fn get_dim_kids(dim_type: &str, dim_name: &str, org: &str, storage: &Storage
             "dim_type" : kids.keys().next().unwrap_or(&"".to_string()),
             "dim_names" : kids.values().next().unwrap_or(&vec![]),
         },
-    })
+    }))
 }
 
-pub fn get_dim_parent(dim_type: &str, dim_name: &str, org: &str, storage: &Storage) -> Value {
+pub fn get_dim_parent(
+    dim_type: &str,
+    dim_name: &str,
+    org: &str,
+    storage: &Storage,
+) -> CubteraResult<Value> {
     let dim = DimBuilder::new(dim_type, org, storage)
         .with_name(dim_name)
-        .read_data()
-        .build();
+        .read_data()?
+        .build()?;
 
-    if let Some(parent) = dim.parent {
+    Ok(if let Some(parent) = dim.parent {
         json!({
             "status": "ok",
             "id": "dimParent",
@@ -113,19 +128,19 @@ pub fn get_dim_parent(dim_type: &str, dim_name: &str, org: &str, storage: &Stora
             "message": "No parent dim found",
             "data": Value::Null,
         })
-    }
+    })
 }
 
-pub fn get_all_orgs(storage: &Storage) -> Value {
+pub fn get_all_orgs(storage: &Storage) -> CubteraResult<Value> {
     let orgs = match storage {
         Storage::DB => {
             let db = GLOBAL_CFG
                 .db_client
                 .clone()
-                .unwrap_or_exit("Can't start DB client".into());
+                .or_error(CubteraError::DataSource, "Can't start DB client".into())?;
             db.list_database_names()
                 .run()
-                .unwrap_or_exit("Can't read list of orgs from DB".into())
+                .or_error(CubteraError::DataSource, "Can't read list of orgs from DB".into())?
         }
         Storage::FS => GLOBAL_CFG.orgs.clone(),
     }
@@ -135,14 +150,14 @@ pub fn get_all_orgs(storage: &Storage) -> Value {
     .map(|dim| dim.to_string())
     .collect::<Vec<String>>();
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "orgs",
         "data": orgs,
-    })
+    }))
 }
 
-pub fn get_dlog_by_keys(org: &str, keys: Vec<String>, limit: Option<i64>) -> Value {
+pub fn get_dlog_by_keys(org: &str, keys: Vec<String>, limit: Option<i64>) -> CubteraResult<Value> {
     let keys: HashMap<String, String> = keys
         .iter()
         .map(|dim: &String| {
@@ -155,18 +170,18 @@ pub fn get_dlog_by_keys(org: &str, keys: Vec<String>, limit: Option<i64>) -> Val
     get_dlog(org, filter.clone(), limit)
 }
 
-pub fn get_dlog(org: &str, filter: Value, limit: Option<i64>) -> Value {
+pub fn get_dlog(org: &str, filter: Value, limit: Option<i64>) -> CubteraResult<Value> {
     let client: Option<mongodb::sync::Client> =
-        GLOBAL_CFG.dlog_db.as_ref().map(|db| db_connect(db));
+        GLOBAL_CFG.dlog_db.as_deref().map(db_connect).transpose()?;
 
     if client.is_none() {
-        return json!({
+        return Ok(json!({
             "status": "error",
             "id": "dlog",
             "message": format!("Can't connect to dlog DB {}", &GLOBAL_CFG.dlog_db.clone().unwrap_or("".to_string())),
             "notes": "Check CUBTERA_DLOG_DB env variable",
             "data": Value::Null,
-        });
+        }));
     }
 
     let db = client.unwrap().database(org);
@@ -178,7 +193,7 @@ pub fn get_dlog(org: &str, filter: Value, limit: Option<i64>) -> Value {
         .sort(mongodb::bson::doc! { "timestamp": -1 })
         .limit(limit.unwrap_or(10))
         .run()
-        .unwrap();
+        .or_error(CubteraError::DataSource, "Can't read dlog from DB".into())?;
 
     let res: Vec<Value> = res
         .collect::<mongodb::error::Result<Vec<mongodb::bson::Bson>>>()
@@ -187,13 +202,13 @@ pub fn get_dlog(org: &str, filter: Value, limit: Option<i64>) -> Value {
         .map(|bson| mongodb::bson::from_bson::<Value>(bson.clone()).unwrap_or_default())
         .collect();
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dlog",
         "data": res,
         "limit": limit,
         "filter": filter,
-    })
+    }))
 }
 
 fn to_dot_notation(value: &Value, prefix: String) -> HashMap<String, Value> {
@@ -220,12 +235,17 @@ fn to_dot_notation(value: &Value, prefix: String) -> HashMap<String, Value> {
     result
 }
 
-pub fn get_all_dim_types(org: &str, storage: &Storage) -> Value {
+pub fn get_all_dim_types(org: &str, storage: &Storage) -> CubteraResult<Value> {
     let dim_types = match storage {
         Storage::DB => {
-            let client = GLOBAL_CFG.db_client.clone().unwrap();
+            let client = GLOBAL_CFG
+                .db_client
+                .clone()
+                .or_error(CubteraError::DataSource, "Can't start DB client".into())?;
             let db = client.database(org);
-            db.list_collection_names().run().unwrap()
+            db.list_collection_names()
+                .run()
+                .or_error(CubteraError::DataSource, "Can't read list of dim types from DB".into())?
         }
         Storage::FS => GLOBAL_CFG.orgs.clone(),
     }
@@ -235,10 +255,10 @@ pub fn get_all_dim_types(org: &str, storage: &Storage) -> Value {
     .map(|dim| dim.to_string())
     .collect::<Vec<String>>();
 
-    json!({
+    Ok(json!({
         "status": "ok",
         "id": "dimTypes",
         "org": org,
         "data": dim_types,
-    })
+    }))
 }
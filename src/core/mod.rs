@@ -1,6 +1,7 @@
 pub mod cfg;
 pub mod dim;
 pub mod dlog;
+pub mod error;
 pub mod im;
 pub mod runner;
 pub mod unit;
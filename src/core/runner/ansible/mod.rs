@@ -0,0 +1,262 @@
+// Ansible runner implementation
+// Generates inventory and extra-vars files from dimensions data
+// and runs ansible-playbook with the command passed from cli
+
+use log::{debug, info};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use yansi::Paint;
+
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::process::run_process;
+
+const INVENTORY_FILE: &str = "cubtera_inventory.json";
+const EXTRA_VARS_FILE: &str = "cubtera_extra_vars.json";
+
+pub struct AnsibleRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for AnsibleRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        AnsibleRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn change_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        debug!(target: "ansible runner", "Generate ansible inventory and extra vars from dims data");
+
+        let temp_folder = &self.load.unit.temp_folder;
+
+        // extra vars: all dim variables (dims with parents) + extensions + unit info
+        let mut extra_vars = std::fs::read_dir(temp_folder)
+            .or_error(
+                CubteraError::Runner,
+                format!("Can't read unit temp folder: {:?}", temp_folder),
+            )?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| entry.is_file())
+            .filter(|entry| entry.extension().unwrap_or_default() == "json")
+            .filter(|entry| {
+                let stem = entry.file_stem().unwrap_or_default().to_string_lossy();
+                stem.starts_with("cubtera_dim_") || stem == "cubtera_ext"
+            })
+            .collect::<Vec<PathBuf>>()
+            .iter()
+            .map(read_json_file)
+            .collect::<CubteraResult<Vec<Option<Value>>>>()?
+            .into_iter()
+            .flatten()
+            .filter_map(|json| json.as_object().cloned())
+            .flatten()
+            .collect::<Map<String, Value>>();
+
+        extra_vars.insert("org_name".into(), json!(GLOBAL_CFG.org));
+        extra_vars.insert("unit_name".into(), json!(self.load.unit.name));
+        extra_vars.insert(
+            "dim_tree".into(),
+            json!(self.load.unit.get_unit_state_path()),
+        );
+
+        std::fs::write(
+            temp_folder.join(EXTRA_VARS_FILE),
+            serde_json::to_string_pretty(&extra_vars)?,
+        )?;
+
+        let groups = self
+            .load
+            .unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| {
+                let kids = self
+                    .load
+                    .unit
+                    .get_all_dims()
+                    .iter()
+                    .filter(|kid| {
+                        kid.parent.as_ref().is_some_and(|p| {
+                            p.dim_type == dim.dim_type && p.dim_name == dim.dim_name
+                        })
+                    })
+                    .map(|kid| (group_name(&kid.dim_type, &kid.dim_name), json!({})))
+                    .collect::<Map<String, Value>>();
+
+                let group = json!({
+                    "hosts": hosts_from_value(dim.get_data().get("hosts")),
+                    "children": kids,
+                    "vars": {
+                        "cubtera_dim_type": dim.dim_type,
+                        "cubtera_dim_name": dim.dim_name,
+                    },
+                });
+                (group_name(&dim.dim_type, &dim.dim_name), group)
+            })
+            .collect::<Map<String, Value>>();
+
+        let inventory = json!({ "all": { "children": groups } });
+
+        std::fs::write(
+            temp_folder.join(INVENTORY_FILE),
+            serde_json::to_string_pretty(&inventory)?,
+        )?;
+
+        self.update_ctx("inventory", json!(INVENTORY_FILE));
+        self.update_ctx("extra_vars", json!(EXTRA_VARS_FILE));
+        self.update_ctx("change_files", json!("executed"));
+
+        Ok(())
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (ansible_path, mut ansible_args) = self
+            .load
+            .params
+            .get_runner_program()?
+            .unwrap_or_else(|| (PathBuf::from("ansible-playbook"), vec![]));
+
+        if self.load.command.is_empty() {
+            return Err(CubteraError::Runner(
+                "Ansible playbook is not defined. Pass it after '--', e.g.: -- site.yml".into(),
+            )
+            .into());
+        }
+
+        ansible_args.extend([
+            "-i".into(),
+            INVENTORY_FILE.into(),
+            "--extra-vars".into(),
+            format!("@{}", EXTRA_VARS_FILE),
+        ]);
+        ansible_args.extend(self.load.command.clone());
+        ansible_args.extend(self.load.params.get_extra_args()?);
+
+        info!(target: "ansible runner", "Command: {} {}",
+            ansible_path.to_string_lossy().blue(),
+            ansible_args.join(" ").blue(),
+        );
+
+        let env_vars = self.get_env_vars();
+
+        let mut ansible_command = Command::new(&ansible_path);
+        ansible_command
+            .current_dir(&self.load.unit.temp_folder)
+            .args(&ansible_args)
+            .envs(env_vars);
+
+        let result = run_process(&mut ansible_command, &self.process_options("runner")).or_error(
+            CubteraError::Runner,
+            format!(
+                "Failed to run {:?} with args {:?}",
+                ansible_path, &ansible_args
+            ),
+        )?;
+        self.check_termination("runner", &result);
+        let exit_code = result.code();
+
+        // dry runs are not deployments, don't save them to dlog
+        let dry_run = ansible_args.iter().any(|arg| {
+            matches!(
+                arg.as_str(),
+                "-C" | "--check"
+                    | "--syntax-check"
+                    | "--list-hosts"
+                    | "--list-tasks"
+                    | "--list-tags"
+            )
+        });
+
+        if GLOBAL_CFG.dlog_db.is_some() && !dry_run {
+            let _ = self
+                .build_dlog("ansible-playbook", exit_code)
+                .put(&GLOBAL_CFG.org)
+                .check_with_warn("Can't put dlog to DB");
+            info!(target: "ansible runner", "Dlog data was saved");
+        }
+
+        self.update_ctx(
+            "runner",
+            json!(format!(
+                "{} {}",
+                ansible_path.to_string_lossy(),
+                ansible_args.join(" ")
+            )),
+        );
+        self.update_ctx("exit_code", json!(exit_code));
+
+        Ok(())
+    }
+}
+
+// Ansible group names allow only letters, digits and underscores
+fn group_name(dim_type: &str, dim_name: &str) -> String {
+    format!("{}_{}", dim_type, dim_name)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Dim "hosts" data could be a list of host names or a map of host names with host vars
+fn hosts_from_value(hosts: Option<&Value>) -> Map<String, Value> {
+    match hosts {
+        Some(Value::Array(list)) => list
+            .iter()
+            .filter_map(|host| host.as_str())
+            .map(|host| (host.to_string(), json!({})))
+            .collect(),
+        Some(Value::Object(map)) => map
+            .iter()
+            .map(|(host, vars)| {
+                let vars = if vars.is_object() {
+                    vars.clone()
+                } else {
+                    json!({})
+                };
+                (host.clone(), vars)
+            })
+            .collect(),
+        _ => Map::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_name() {
+        assert_eq!(group_name("dc", "stg1-use1"), "dc_stg1_use1");
+        assert_eq!(group_name("env", "prod.eu"), "env_prod_eu");
+    }
+
+    #[test]
+    fn test_hosts_from_value() {
+        let list = json!(["web1", "web2", 3]);
+        let hosts = hosts_from_value(Some(&list));
+        assert_eq!(hosts.len(), 2);
+        assert_eq!(hosts["web1"], json!({}));
+
+        let map = json!({ "db1": { "ansible_host": "10.0.0.1" }, "db2": null });
+        let hosts = hosts_from_value(Some(&map));
+        assert_eq!(hosts["db1"]["ansible_host"], "10.0.0.1");
+        assert_eq!(hosts["db2"], json!({}));
+
+        assert!(hosts_from_value(None).is_empty());
+    }
+}
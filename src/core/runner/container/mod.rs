@@ -0,0 +1,302 @@
+// Container runner implementation
+// Runs the runner command inside a container image with a configurable engine (docker, podman, nerdctl).
+// Unit temp folder is mounted to the same path and used as working directory,
+// so generated files and CUBTERA_DIM_*_JSON paths are the same as on the host.
+
+use log::info;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use yansi::Paint;
+
+use super::{env, runner_command_line, Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::command::{quote_args, CommandLine};
+use crate::utils::process::run_process;
+
+pub struct ContainerRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for ContainerRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        ContainerRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let params = &self.load.params;
+        let image = params.get_image().or_error(
+            CubteraError::Runner,
+            "Container image is not defined. Set image param in unit manifest or global config"
+                .into(),
+        )?;
+        let engine = string_to_path(&params.engine);
+        let workdir = self.load.unit.temp_folder.to_string_lossy().to_string();
+
+        // runner_command is executed inside the container, otherwise cli command is passed to the image entrypoint
+        let command = match &params.runner_command {
+            Some(runner_command) => {
+                match runner_command_line(
+                    runner_command,
+                    &self.load.command,
+                    params.extra_args.as_ref(),
+                    params.is_shell(),
+                )? {
+                    CommandLine::Line(line) => vec!["sh".into(), "-c".into(), line],
+                    CommandLine::Argv(argv) => argv,
+                }
+            }
+            None => [self.load.command.clone(), params.get_extra_args()?].concat(),
+        };
+
+        // engine gets values of passed env vars from its own env, they are not visible in the command line
+        let env_vars = self.get_env_vars();
+        let mut env_names = vec!["CUBTERA_RUNNER_CMD".to_string()];
+        if params.is_dim_env_vars() {
+            env_names.extend(
+                env::unit_env_vars(
+                    &self.load.unit,
+                    &params.get_dim_env_prefix(),
+                    params.get_dim_env_depth(),
+                )
+                .into_keys(),
+            );
+        }
+        env_names.extend(
+            env_vars
+                .keys()
+                .filter(|name| {
+                    params
+                        .container_env
+                        .iter()
+                        .flatten()
+                        .any(|pattern| env_allowed(name, pattern))
+                })
+                .cloned(),
+        );
+        env_names.sort();
+        env_names.dedup();
+
+        let engine_args = run_args(
+            &workdir,
+            &self.symlink_mounts(),
+            &env_names,
+            &params.get_container_args()?,
+            &image,
+            &command,
+        )?;
+
+        info!(target: "container runner", "Image: {}", image.blue());
+        info!(target: "container runner", "Command: {} {}", engine.to_string_lossy().blue(), quote_args(&command).blue());
+
+        let mut engine_command = Command::new(&engine);
+        engine_command
+            .current_dir(&self.load.unit.temp_folder)
+            .args(&engine_args)
+            .envs(env_vars);
+
+        let result = run_process(&mut engine_command, &self.process_options("runner")).or_error(
+            CubteraError::Runner,
+            format!("Failed to run {:?} with args {:?}", engine, &engine_args),
+        )?;
+        self.check_termination("runner", &result);
+
+        self.update_ctx("image", json!(image));
+        self.update_ctx("runner", json!(quote_args(&command)));
+        self.update_ctx("exit_code", json!(result.code()));
+
+        Ok(())
+    }
+}
+
+impl ContainerRunner {
+    // Targets of top level symlinks in temp folder (e.g. modules), mounted read only to the same path
+    fn symlink_mounts(&self) -> Vec<PathBuf> {
+        std::fs::read_dir(&self.load.unit.temp_folder)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| std::fs::read_link(entry.path()).ok())
+            .filter(|target| target.is_absolute() && target.exists())
+            .collect()
+    }
+}
+
+// Engine args: run --rm --mount <workdir> -w <workdir> [--mount <symlink target>,readonly] [-e NAME] [container_args] <image> [command]
+// --mount is used instead of -v because temp folder path contains ':' (e.g. dc:stg1-use2)
+fn run_args(
+    workdir: &str,
+    mounts: &[PathBuf],
+    env_names: &[String],
+    container_args: &[String],
+    image: &str,
+    command: &[String],
+) -> CubteraResult<Vec<String>> {
+    let workdir = workdir.trim_end_matches('/');
+    let mut args = vec![
+        "run".to_string(),
+        "--rm".into(),
+        "--mount".into(),
+        mount_arg(workdir, false)?,
+        "-w".into(),
+        workdir.into(),
+    ];
+    for mount in mounts {
+        args.extend(["--mount".into(), mount_arg(&mount.to_string_lossy(), true)?]);
+    }
+    for name in env_names {
+        args.extend(["-e".into(), name.clone()]);
+    }
+    args.extend_from_slice(container_args);
+    args.push(image.into());
+    args.extend_from_slice(command);
+    Ok(args)
+}
+
+// Bind mount to the same path. --mount options are comma separated and engines don't agree on quoting,
+// so paths with ',' can't be mounted
+fn mount_arg(path: &str, readonly: bool) -> CubteraResult<String> {
+    if path.contains(',') {
+        return Err(CubteraError::Runner(format!(
+            "Can't mount {path:?} to the container: path contains ','"
+        )));
+    }
+    Ok(match readonly {
+        true => format!("type=bind,source={path},target={path},readonly"),
+        false => format!("type=bind,source={path},target={path}"),
+    })
+}
+
+// Env var name matches allow-list pattern: exact name or prefix with trailing "*", e.g. "AWS_*"
+fn env_allowed(name: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        let args = run_args(
+            "/tmp/unit/dc:stg1/",
+            &[PathBuf::from("/work/modules")],
+            &["AWS_REGION".into()],
+            &["--network".into(), "host".into()],
+            "hashicorp/terraform:1.7.5",
+            &["plan".into(), "-input=false".into()],
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "--mount",
+                "type=bind,source=/tmp/unit/dc:stg1,target=/tmp/unit/dc:stg1",
+                "-w",
+                "/tmp/unit/dc:stg1",
+                "--mount",
+                "type=bind,source=/work/modules,target=/work/modules,readonly",
+                "-e",
+                "AWS_REGION",
+                "--network",
+                "host",
+                "hashicorp/terraform:1.7.5",
+                "plan",
+                "-input=false",
+            ]
+        );
+
+        assert!(run_args("/tmp/unit/a,b", &[], &[], &[], "alpine", &[]).is_err());
+
+        assert!(env_allowed("AWS_PROFILE", "AWS_*"));
+        assert!(env_allowed("HOME", "HOME"));
+        assert!(!env_allowed("HOMEDIR", "HOME"));
+    }
+
+    #[test]
+    fn test_runner_with_stub_engine() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let temp_folder = dir.path().join("unit");
+        let modules = dir.path().join("modules");
+        std::fs::create_dir_all(&temp_folder).unwrap();
+        std::fs::create_dir_all(&modules).unwrap();
+        std::os::unix::fs::symlink(&modules, temp_folder.join("modules")).unwrap();
+
+        // stub engine saves its argv and values of passed env vars
+        let engine = dir.path().join("engine.sh");
+        std::fs::write(
+            &engine,
+            "#!/bin/sh\nprintf '%s\\n' \"$@\" > engine_args.txt\necho \"$CUBTERA_RUNNER_CMD\" > engine_env.txt\nexit 3\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&engine, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let params = serde_json::from_value(json!({
+            "engine": engine.to_string_lossy(),
+            "image": "alpine",
+            "image_tag": "3.20",
+            "container_env": ["PATH"],
+            "dim_env_vars": "false",
+        }))
+        .unwrap();
+        let load = RunnerLoad {
+            unit: Unit::for_test("app", "container", &temp_folder, &temp_folder),
+            command: vec!["plan".into(), "-lock=false".into()],
+            params: super::super::params::RunnerParams::init(params).unwrap(),
+            state_backend: Value::Null,
+            dependencies: Value::Null,
+        };
+
+        let mut runner = <ContainerRunner as Runner>::new(load);
+        runner.runner().unwrap();
+        assert_eq!(runner.get_ctx()["exit_code"], 3);
+        assert_eq!(runner.get_ctx()["image"], "alpine:3.20");
+
+        let workdir = temp_folder.to_string_lossy().to_string();
+        let modules = modules.to_string_lossy().to_string();
+        let args = std::fs::read_to_string(temp_folder.join("engine_args.txt")).unwrap();
+        assert_eq!(
+            args.lines().collect::<Vec<&str>>(),
+            [
+                "run",
+                "--rm",
+                "--mount",
+                &format!("type=bind,source={workdir},target={workdir}"),
+                "-w",
+                &workdir,
+                "--mount",
+                &format!("type=bind,source={modules},target={modules},readonly"),
+                "-e",
+                "CUBTERA_RUNNER_CMD",
+                "-e",
+                "PATH",
+                "alpine:3.20",
+                "plan",
+                "-lock=false",
+            ]
+        );
+        let env = std::fs::read_to_string(temp_folder.join("engine_env.txt")).unwrap();
+        assert_eq!(env.trim(), "plan '-lock=false'");
+    }
+}
@@ -0,0 +1,130 @@
+// Unit and dimension values as env vars for runner and hook processes
+use crate::prelude::*;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Env vars with unit and dimension values, e.g. for prefix `CUBTERA`:
+/// `CUBTERA_ORG`, `CUBTERA_UNIT_NAME`, `CUBTERA_DIM_TREE`,
+/// `CUBTERA_DIM_DC_NAME`, `CUBTERA_DIM_DC_META_REGION` (parents included),
+/// `CUBTERA_EXT_INDEX_NAME` and `CUBTERA_DIM_DC_JSON` with the path of the dim json file.
+/// Objects nested deeper than `depth` are exported as json strings, arrays are always json.
+pub(super) fn unit_env_vars(unit: &Unit, prefix: &str, depth: usize) -> HashMap<String, String> {
+    let mut env_vars = HashMap::new();
+    env_vars.insert(env_name(&[prefix, "ORG"]), GLOBAL_CFG.org.clone());
+    env_vars.insert(env_name(&[prefix, "UNIT_NAME"]), unit.name.clone());
+    env_vars.insert(env_name(&[prefix, "DIM_TREE"]), unit.get_unit_state_path());
+
+    for dim in unit.get_all_dims() {
+        let dim_prefix = env_name(&[prefix, "DIM", &dim.dim_type]);
+        flatten(&dim_prefix, &dim.get_dim_data(), depth, &mut env_vars);
+    }
+
+    for ext in &unit.extensions {
+        if let Some((ext_type, ext_name)) = ext.split_once(':') {
+            env_vars.insert(
+                env_name(&[prefix, "EXT", ext_type, "NAME"]),
+                ext_name.into(),
+            );
+        }
+    }
+
+    // generated json files, tf runner renames them to *.auto.tfvars.json
+    for file in std::fs::read_dir(&unit.temp_folder)
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let file_name = file.file_name().to_string_lossy().to_string();
+        let Some(stem) = file_name
+            .strip_suffix(".auto.tfvars.json")
+            .or(file_name.strip_suffix(".json"))
+        else {
+            continue;
+        };
+        let key = match stem.strip_prefix("cubtera_dim_") {
+            Some(dim_type) => env_name(&[prefix, "DIM", dim_type, "JSON"]),
+            None if stem == "cubtera_ext" => env_name(&[prefix, "EXT_JSON"]),
+            None => continue,
+        };
+        env_vars.insert(key, file.path().to_string_lossy().to_string());
+    }
+
+    env_vars
+}
+
+fn flatten(name: &str, value: &Value, depth: usize, env_vars: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) if depth > 0 => map
+            .iter()
+            .for_each(|(key, value)| flatten(&env_name(&[name, key]), value, depth - 1, env_vars)),
+        Value::String(s) => {
+            env_vars.insert(name.into(), s.clone());
+        }
+        Value::Null => {
+            env_vars.insert(name.into(), String::new());
+        }
+        _ => {
+            env_vars.insert(name.into(), value.to_string());
+        }
+    }
+}
+
+// Upper case parts joined with "_", any char not allowed in env var names is replaced with "_"
+fn env_name(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.chars()
+                .map(|c| {
+                    if c.is_ascii_alphanumeric() {
+                        c.to_ascii_uppercase()
+                    } else {
+                        '_'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten() {
+        let data = json!({
+            "name": "stg1-use2",
+            "meta": {
+                "region": "us-east-2",
+                "vpc-cidr": "10.0.0.0/16",
+                "prod": false,
+                "azs": ["a", "b"],
+                "tags": { "team": { "name": "infra" } },
+                "owner": null,
+            }
+        });
+        let mut env_vars = HashMap::new();
+        flatten("CUBTERA_DIM_DC", &data, 3, &mut env_vars);
+
+        assert_eq!(env_vars["CUBTERA_DIM_DC_NAME"], "stg1-use2");
+        assert_eq!(env_vars["CUBTERA_DIM_DC_META_REGION"], "us-east-2");
+        assert_eq!(env_vars["CUBTERA_DIM_DC_META_VPC_CIDR"], "10.0.0.0/16");
+        assert_eq!(env_vars["CUBTERA_DIM_DC_META_PROD"], "false");
+        assert_eq!(env_vars["CUBTERA_DIM_DC_META_AZS"], r#"["a","b"]"#);
+        assert_eq!(
+            env_vars["CUBTERA_DIM_DC_META_TAGS_TEAM"],
+            r#"{"name":"infra"}"#
+        );
+        assert_eq!(env_vars["CUBTERA_DIM_DC_META_OWNER"], "");
+
+        let mut env_vars = HashMap::new();
+        flatten("DC", &data, 1, &mut env_vars);
+        assert_eq!(env_vars.len(), 2);
+        assert!(env_vars["DC_META"].contains(r#""region":"us-east-2""#));
+        assert_eq!(env_name(&["", "dim", "dc.1"]), "DIM_DC_1");
+    }
+}
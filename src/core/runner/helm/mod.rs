@@ -0,0 +1,373 @@
+// Helm runner implementation
+// Converts dimension data files into layered helm values files
+// and runs helm with release name and namespace templated from the dim tree
+
+use log::{debug, info};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use yansi::Paint;
+
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::process::run_process;
+
+const DEFAULT_RELEASE_NAME: &str = "{{ unit_name }}";
+const DEFAULT_CHART: &str = ".";
+
+pub struct HelmRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for HelmRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        HelmRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn change_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        debug!(target: "helm runner", "Convert dims data files into helm values files");
+
+        let temp_folder = &self.load.unit.temp_folder;
+
+        // read all cubtera_dim_<dim_type>.json files
+        let files = std::fs::read_dir(temp_folder)
+            .or_error(
+                CubteraError::Runner,
+                format!("Can't read unit temp folder: {:?}", temp_folder),
+            )?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| entry.is_file())
+            .filter(|entry| entry.extension().unwrap_or_default() == "json")
+            .filter(|entry| {
+                entry
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .starts_with("cubtera_dim_")
+            })
+            .collect::<Vec<PathBuf>>();
+
+        // merge all dim variables into one map, every file contains dim + parents values
+        let mut dim_vars = Map::new();
+        let mut file_dim_types = Vec::new();
+        for file in &files {
+            if let Some(Value::Object(obj)) = read_json_file(file)? {
+                dim_vars.extend(obj);
+            }
+            let dim_type = file
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .trim_start_matches("cubtera_dim_")
+                .to_string();
+            file_dim_types.push(dim_type);
+        }
+        file_dim_types.sort();
+
+        // parent dims first, so child values are applied on top of them
+        let mut dim_types = self
+            .load
+            .unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| dim.dim_type.clone())
+            .collect::<Vec<String>>();
+        for dim_type in file_dim_types {
+            if !dim_types.contains(&dim_type) {
+                dim_types.push(dim_type);
+            }
+        }
+
+        let mut values_files = Vec::new();
+        for (index, (dim_type, values)) in split_dim_vars_by_type(&dim_vars, &dim_types)
+            .into_iter()
+            .enumerate()
+        {
+            let file_name = format!("cubtera_values_{:02}_{}.json", index, dim_type);
+            std::fs::write(
+                temp_folder.join(&file_name),
+                serde_json::to_string_pretty(&values)?,
+            )?;
+            values_files.push(file_name);
+        }
+
+        // extensions values are applied last
+        if temp_folder.join("cubtera_ext.json").exists() {
+            values_files.push("cubtera_ext.json".into());
+        }
+
+        for file in &files {
+            std::fs::remove_file(file).or_error(
+                CubteraError::Runner,
+                format!("Can't remove file: {:?}", file),
+            )?;
+        }
+
+        self.update_ctx("values_files", json!(values_files));
+        self.update_ctx("change_files", json!("executed"));
+
+        Ok(())
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (helm_path, mut helm_args) = self
+            .load
+            .params
+            .get_runner_program()?
+            .unwrap_or_else(|| (PathBuf::from("helm"), vec![]));
+
+        let release_name = self.get_release_name()?;
+        let namespace = self.get_namespace()?;
+        let chart = self.get_chart()?;
+
+        let values_args = self
+            .get_ctx()
+            .get("values_files")
+            .and_then(|files| files.as_array().cloned())
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|file| file.as_str())
+            .flat_map(|file| ["-f".to_string(), file.to_string()])
+            .collect::<Vec<String>>();
+
+        let (command, args) = match self.load.command.split_first() {
+            Some((command, args)) => (command.clone(), args.to_vec()),
+            None => ("template".to_string(), vec![]),
+        };
+        let command = command.as_str();
+
+        let command_args: Vec<String> = match command {
+            "install" | "upgrade" | "template" => {
+                [command.to_string(), release_name.clone(), chart]
+                    .into_iter()
+                    .chain(values_args)
+                    .collect()
+            }
+            "diff" => [
+                "diff".to_string(),
+                "upgrade".into(),
+                release_name.clone(),
+                chart,
+            ]
+            .into_iter()
+            .chain(values_args)
+            .collect(),
+            "lint" => ["lint".to_string(), chart]
+                .into_iter()
+                .chain(values_args)
+                .collect(),
+            "uninstall" | "status" | "history" | "rollback" | "get" => {
+                vec![command.to_string(), release_name.clone()]
+            }
+            _ => vec![command.to_string()],
+        };
+        helm_args.extend(command_args);
+        helm_args.extend(args);
+
+        if let Some(namespace) = &namespace {
+            helm_args.extend(["--namespace".to_string(), namespace.clone()]);
+        }
+
+        helm_args.extend(self.load.params.get_extra_args()?);
+
+        info!(target: "helm runner", "Release: {} | Namespace: {}",
+            release_name.blue(),
+            namespace.clone().unwrap_or("default".into()).blue(),
+        );
+        info!(target: "helm runner", "Command: {} {}",
+            helm_path.to_string_lossy().blue(),
+            helm_args.join(" ").blue(),
+        );
+
+        let mut helm_command = Command::new(&helm_path);
+        helm_command
+            .current_dir(&self.load.unit.temp_folder)
+            .args(&helm_args);
+
+        let result = run_process(&mut helm_command, &self.process_options("runner")).or_error(
+            CubteraError::Runner,
+            format!("Failed to run {:?} with args {:?}", helm_path, &helm_args),
+        )?;
+        self.check_termination("runner", &result);
+        let exit_code = result.code();
+
+        let dlog_command = GLOBAL_CFG
+            .dlog_db
+            .clone()
+            .and(matches!(command, "install" | "upgrade" | "uninstall").then_some(command));
+
+        if let Some(dlog_command) = dlog_command {
+            let _ = self
+                .build_dlog(dlog_command, exit_code)
+                .put(&GLOBAL_CFG.org)
+                .check_with_warn("Can't put dlog to DB");
+            info!(target: "helm runner", "Dlog data was saved");
+        }
+
+        self.update_ctx("release_name", json!(release_name));
+        self.update_ctx("namespace", json!(namespace));
+        self.update_ctx("exit_code", json!(exit_code));
+
+        Ok(())
+    }
+}
+
+impl HelmRunner {
+    // Map of dim_type => dim_name for all unit dims with parents
+    fn get_dims_map(&self) -> Map<String, Value> {
+        self.load
+            .unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| (dim.dim_type.clone(), json!(dim.dim_name)))
+            .collect()
+    }
+
+    fn render_template(&self, template: &str) -> CubteraResult<String> {
+        let mut handlebars = handlebars::Handlebars::new();
+        handlebars.set_strict_mode(true);
+        // chart can be a path or url, html escaping would break it
+        handlebars.register_escape_fn(handlebars::no_escape);
+
+        let data = json!({
+            "org": &GLOBAL_CFG.org,
+            "unit_name": &self.load.unit.name,
+            "dim_tree": self.load.unit.get_unit_state_path(),
+            "dims": self.get_dims_map(),
+        });
+
+        handlebars.render_template(template, &data).or_error(
+            CubteraError::Runner,
+            format!("Failed to render helm runner template: {template}"),
+        )
+    }
+
+    fn get_chart(&self) -> CubteraResult<String> {
+        let template = self
+            .load
+            .params
+            .chart
+            .clone()
+            .unwrap_or(DEFAULT_CHART.into());
+        self.render_template(&template)
+    }
+
+    fn get_release_name(&self) -> CubteraResult<String> {
+        let template = self
+            .load
+            .params
+            .release_name
+            .clone()
+            .unwrap_or(DEFAULT_RELEASE_NAME.into());
+        Ok(to_dns_label(&self.render_template(&template)?))
+    }
+
+    fn get_namespace(&self) -> CubteraResult<Option<String>> {
+        self.load
+            .params
+            .namespace
+            .as_ref()
+            .map(|template| Ok(to_dns_label(&self.render_template(template)?)))
+            .transpose()
+    }
+}
+
+// Split flat dim variables (dim_<dim_type>_<key>) into values per dim type.
+// Keys are matched with the longest dim type prefix to avoid collisions like "dc" and "dc_zone"
+fn split_dim_vars_by_type(
+    dim_vars: &Map<String, Value>,
+    dim_types: &[String],
+) -> Vec<(String, Value)> {
+    let mut by_length = dim_types.to_vec();
+    by_length.sort_by_key(|dim_type| std::cmp::Reverse(dim_type.len()));
+
+    let mut layers: Vec<(String, Map<String, Value>)> = dim_types
+        .iter()
+        .map(|dim_type| (dim_type.clone(), Map::new()))
+        .collect();
+
+    dim_vars.iter().for_each(|(key, value)| {
+        let dim_type = by_length
+            .iter()
+            .find(|dim_type| key.starts_with(&format!("dim_{}_", dim_type)));
+        if let Some(dim_type) = dim_type {
+            if let Some((_, layer)) = layers.iter_mut().find(|(t, _)| t == dim_type) {
+                layer.insert(key.clone(), value.clone());
+            }
+        }
+    });
+
+    layers
+        .into_iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(dim_type, values)| (dim_type, Value::Object(values)))
+        .collect()
+}
+
+// Helm release names and namespaces must be valid DNS labels
+fn to_dns_label(s: &str) -> String {
+    s.to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect::<String>()
+        .trim_matches('-')
+        .chars()
+        .take(53)
+        .collect::<String>()
+        .trim_end_matches('-')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_dim_vars_by_type() {
+        let dim_vars = json!({
+            "dim_dc_name": "stg1-use1",
+            "dim_dc_zone_name": "a",
+            "dim_env_name": "stg1",
+            "dim_dome_name": "stg",
+            "unknown": true,
+        });
+        let dim_types = vec![
+            "dome".to_string(),
+            "env".to_string(),
+            "dc".to_string(),
+            "dc_zone".to_string(),
+        ];
+
+        let layers = split_dim_vars_by_type(dim_vars.as_object().unwrap(), &dim_types);
+        let types = layers
+            .iter()
+            .map(|(t, _)| t.as_str())
+            .collect::<Vec<&str>>();
+
+        assert_eq!(types, vec!["dome", "env", "dc", "dc_zone"]);
+        assert_eq!(layers[2].1, json!({ "dim_dc_name": "stg1-use1" }));
+        assert_eq!(layers[3].1, json!({ "dim_dc_zone_name": "a" }));
+    }
+
+    #[test]
+    fn test_to_dns_label() {
+        assert_eq!(to_dns_label("App_Unit:stg1-use1"), "app-unit-stg1-use1");
+        assert_eq!(to_dns_label("-unit/dc:stg-"), "unit-dc-stg");
+        assert_eq!(to_dns_label(&"a".repeat(60)).len(), 53);
+    }
+}
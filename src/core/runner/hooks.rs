@@ -0,0 +1,154 @@
+use crate::utils::command::CommandLine;
+use serde::{Deserialize, Serialize};
+
+/// Runner lifecycle step the hook is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStep {
+    /// before the runner
+    Inlet,
+    /// after the runner finished successfully
+    Outlet,
+    /// after inlet, runner or outlet failure, cancellation or timeout
+    OnFailure,
+    /// after the run, regardless of how it finished
+    Always,
+}
+
+impl HookStep {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStep::Inlet => "inlet",
+            HookStep::Outlet => "outlet",
+            HookStep::OnFailure => "on_failure",
+            HookStep::Always => "always",
+        }
+    }
+
+    // Hooks after the run get its status and don't stop each other on failure
+    pub fn is_final(&self) -> bool {
+        matches!(self, HookStep::OnFailure | HookStep::Always)
+    }
+}
+
+/// Lifecycle hook from `[[runner.hooks]]` section of global config or unit manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub command: CommandLine,
+    pub step: HookStep,
+    // run with `/bin/sh -c`, runner `shell` param if not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shell: Option<bool>,
+    // runner commands (first cli command arg) the hook runs for, all commands if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub runner_commands: Vec<String>,
+    #[serde(default)]
+    pub continue_on_error: bool,
+}
+
+impl Hook {
+    pub fn new(name: &str, command: CommandLine, step: HookStep) -> Self {
+        Hook {
+            name: Some(name.into()),
+            command,
+            step,
+            shell: None,
+            runner_commands: vec![],
+            continue_on_error: false,
+        }
+    }
+
+    pub fn get_name(&self) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| self.command.to_string())
+    }
+
+    pub fn is_enabled_for(&self, step: HookStep, runner_command: &str) -> bool {
+        self.step == step
+            && (self.runner_commands.is_empty()
+                || self.runner_commands.iter().any(|c| c == runner_command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_deserialize() {
+        let hooks: Vec<Hook> = toml::from_str::<toml::Table>(
+            r#"
+            [[hooks]]
+            name = "notify"
+            command = ["./notify.sh", "--channel", "ops team"]
+            step = "always"
+            runner_commands = ["apply", "destroy"]
+            continue_on_error = true
+
+            [[hooks]]
+            command = "./unlock.sh | tee unlock.log"
+            step = "on_failure"
+            shell = true
+            "#,
+        )
+        .unwrap()["hooks"]
+            .clone()
+            .try_into()
+            .unwrap();
+
+        assert_eq!(hooks[0].step, HookStep::Always);
+        assert!(hooks[0].continue_on_error);
+        assert_eq!(hooks[0].command.argv().unwrap()[2], "ops team");
+        assert_eq!(hooks[0].shell, None);
+        assert_eq!(hooks[1].get_name(), "./unlock.sh | tee unlock.log");
+        assert_eq!(hooks[1].shell, Some(true));
+        assert!(!hooks[1].continue_on_error);
+    }
+
+    #[test]
+    fn test_hook_is_enabled_for() {
+        let mut hook = Hook::new("plan", "echo".into(), HookStep::Outlet);
+        assert!(hook.is_enabled_for(HookStep::Outlet, "plan"));
+        assert!(!hook.is_enabled_for(HookStep::Inlet, "plan"));
+
+        hook.runner_commands = vec!["apply".into(), "destroy".into()];
+        assert!(hook.is_enabled_for(HookStep::Outlet, "destroy"));
+        assert!(!hook.is_enabled_for(HookStep::Outlet, "plan"));
+    }
+
+    #[test]
+    fn test_get_hooks_order() {
+        let params = super::super::params::RunnerParams::init(
+            [
+                ("outlet_command".to_string(), serde_json::json!("./legacy.sh")),
+                (
+                    "hooks".to_string(),
+                    serde_json::json!([
+                        {"command": "./apply_only.sh", "step": "outlet", "runner_commands": ["apply"]},
+                        {"command": "./all.sh", "step": "outlet"},
+                    ]),
+                ),
+                ("lock_timeout".to_string(), serde_json::json!(600)),
+            ]
+            .into(),
+        )
+        .unwrap();
+
+        let names = |command: &str| -> Vec<String> {
+            params
+                .get_hooks(HookStep::Outlet, command)
+                .iter()
+                .map(|h| h.command.to_string())
+                .collect()
+        };
+        assert_eq!(
+            names("apply"),
+            ["./legacy.sh", "./apply_only.sh", "./all.sh"]
+        );
+        assert_eq!(names("plan"), ["./legacy.sh", "./all.sh"]);
+        assert_eq!(params.lock_timeout, "600");
+    }
+}
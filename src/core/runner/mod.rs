@@ -1,23 +1,48 @@
+mod ansible;
 mod bash;
+mod container;
+mod env;
+mod helm;
+mod hooks;
+mod packer;
 mod params;
+mod plugin;
+mod pulumi;
+mod template;
 #[allow(clippy::option_map_unit_fn)]
 mod tf;
 mod tofu;
 
 use crate::prelude::*;
+use crate::utils::command::{quote_args, CommandLine};
+use crate::utils::output::OutputCapture;
+use crate::utils::process::{ProcessOptions, ProcessResult};
+use hooks::{Hook, HookStep};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::path::PathBuf;
+
+const OUTPUT_LOG_FILE: &str = "cubtera_output.log";
 
 // add new runner here
-fn runner_create(runner_type: RunnerType, load: RunnerLoad) -> Box<dyn Runner> {
-    match runner_type {
+fn runner_create(runner_type: RunnerType, load: RunnerLoad) -> CubteraResult<Box<dyn Runner>> {
+    Ok(match runner_type {
         RunnerType::TF => Box::new(tf::TfRunner::new(load)),
         RunnerType::BASH => Box::new(bash::BashRunner::new(load)),
         RunnerType::TOFU => Box::new(tofu::TofuRunner::new(load)),
-        _ => exit_with_error(format!(
-            "Unknown runner type: {runner_type:?}. Check documentation about supported runners"
-        )),
-    }
+        RunnerType::HELM => Box::new(helm::HelmRunner::new(load)),
+        RunnerType::ANSIBLE => Box::new(ansible::AnsibleRunner::new(load)),
+        RunnerType::CONTAINER => Box::new(container::ContainerRunner::new(load)),
+        RunnerType::TEMPLATE => Box::new(template::TemplateRunner::new(load)),
+        RunnerType::PULUMI => Box::new(pulumi::PulumiRunner::new(load)),
+        RunnerType::PACKER => Box::new(packer::PackerRunner::new(load)),
+        RunnerType::PLUGIN => Box::new(plugin::PluginRunner::new(load)),
+        _ => {
+            return Err(CubteraError::Runner(format!(
+                "Unknown runner type: {runner_type:?}. Check documentation about supported runners or set runner plugin in global config"
+            )))
+        }
+    })
 }
 
 #[derive(Debug)]
@@ -26,6 +51,7 @@ pub struct RunnerLoad {
     command: Vec<String>,         // command from cli
     params: params::RunnerParams, // HashMap<String, String>, // runner params from unit manifest and global config
     state_backend: Value,
+    dependencies: Value, // state backends of units from manifest depends_on list
 }
 
 #[derive(Debug, Clone)]
@@ -33,6 +59,13 @@ pub enum RunnerType {
     TF,
     BASH,
     TOFU,
+    HELM,
+    ANSIBLE,
+    CONTAINER,
+    TEMPLATE,
+    PULUMI,
+    PACKER,
+    PLUGIN,
     UNKNOWN,
 }
 
@@ -42,6 +75,12 @@ impl RunnerType {
             "TF" => RunnerType::TF,
             "BASH" => RunnerType::BASH,
             "TOFU" => RunnerType::TOFU,
+            "HELM" => RunnerType::HELM,
+            "ANSIBLE" => RunnerType::ANSIBLE,
+            "CONTAINER" => RunnerType::CONTAINER,
+            "TEMPLATE" => RunnerType::TEMPLATE,
+            "PULUMI" => RunnerType::PULUMI,
+            "PACKER" => RunnerType::PACKER,
             _ => RunnerType::UNKNOWN,
         }
     }
@@ -58,8 +97,8 @@ pub trait Runner {
     fn copy_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         debug!(target: "runner", "Default copy_files method.");
 
-        self.get_load().unit.remove_temp_folder();
-        self.get_load().unit.copy_files();
+        self.get_load().unit.remove_temp_folder()?;
+        self.get_load().unit.copy_files()?;
 
         self.update_ctx("copy_files", json!("executed"));
         let working_dir = self
@@ -82,7 +121,7 @@ pub trait Runner {
 
     fn inlet(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         debug!(target: "runner", "Default inlet method.");
-        self.executor("inlet")?;
+        self.run_hooks(HookStep::Inlet)?;
 
         Ok(())
     }
@@ -96,7 +135,7 @@ pub trait Runner {
 
     fn outlet(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         debug!(target: "runner", "Default outlet method.");
-        self.executor("outlet")?;
+        self.run_hooks(HookStep::Outlet)?;
 
         Ok(())
     }
@@ -111,15 +150,230 @@ pub trait Runner {
 
     fn run(&mut self) -> Result<Value, Box<dyn std::error::Error>> {
         self.copy_files()?;
+        self.init_output_log()?;
         self.change_files()?;
-        self.inlet()?;
-        self.runner()?;
-        self.outlet()?;
+
+        let result = self.run_steps();
+        let run_status = match self.get_ctx().pointer("/termination/reason").and_then(|r| r.as_str()) {
+            Some(reason) => reason.to_string(),
+            None if result.is_err() || self.is_failed() => "failure".into(),
+            None => "success".into(),
+        };
+        self.update_ctx("run_status", json!(run_status));
+
+        // on_failure and always hooks run even if the run failed, first error is returned after them
+        let final_result = if run_status == "success" {
+            Ok(())
+        } else {
+            self.run_hooks(HookStep::OnFailure)
+        };
+        let final_result = self.run_hooks(HookStep::Always).and(final_result);
+        result?;
+        final_result?;
         self.logger()?;
 
         Ok(self.get_ctx().clone())
     }
 
+    // Prepares unit temp folder with generated files without running any command or hook
+    fn render(&mut self) -> Result<Value, Box<dyn std::error::Error>> {
+        self.copy_files()?;
+        self.change_files()?;
+        self.update_ctx("render", json!("executed"));
+
+        Ok(self.get_ctx().clone())
+    }
+
+    fn run_steps(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.inlet()?;
+        // cancelled or timed out step stops the run
+        if !self.is_terminated() {
+            self.runner()?;
+        }
+        // outlet runs only after successful runner
+        if !self.is_failed() {
+            self.outlet()?;
+        }
+
+        Ok(())
+    }
+
+    // Runs hooks of the step in order. Failed inlet or outlet hook stops the step
+    // unless continue_on_error is set, on_failure and always hooks are all executed.
+    fn run_hooks(&mut self, step: HookStep) -> Result<(), Box<dyn std::error::Error>> {
+        let runner_command = self.get_load().command.first().cloned().unwrap_or_default();
+        let hooks = self.get_load().params.get_hooks(step, &runner_command);
+
+        let mut first_error = None;
+        for hook in hooks {
+            if !step.is_final() && self.is_terminated() {
+                break;
+            }
+            let Err(e) = self.run_hook(&hook) else {
+                continue;
+            };
+            if hook.continue_on_error {
+                warn!(target: "runner", "{e}. Continue on error");
+            } else if !step.is_final() {
+                return Err(e);
+            } else if first_error.is_none() {
+                first_error = Some(e);
+            } else {
+                warn!(target: "runner", "{e}");
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+
+    fn run_hook(&mut self, hook: &Hook) -> Result<(), Box<dyn std::error::Error>> {
+        use yansi::Paint; // don't move this to the top - conflicts with a crate
+
+        let step = hook.step.as_str();
+        let dir = self
+            .get_load()
+            .unit
+            .temp_folder
+            .clone()
+            .to_string_lossy()
+            .to_string();
+
+        match &hook.name {
+            Some(name) => info!(target: "runner", "{} hook {}: {}", capitalize_first(step), name.green(), hook.command.blue()),
+            None => info!(target: "runner", "{} hook: {}", capitalize_first(step), hook.command.blue()),
+        }
+
+        let mut env_vars = self.get_env_vars();
+        if hook.step.is_final() {
+            let ctx = self.get_ctx();
+            let run_status = ctx["run_status"].as_str().unwrap_or_default().to_string();
+            let exit_code = ctx
+                .get("exit_code")
+                .map(|c| c.to_string())
+                .unwrap_or_else(|| if run_status == "success" { "0" } else { "1" }.into());
+            env_vars.insert("CUBTERA_RUN_STATUS".into(), run_status);
+            env_vars.insert("CUBTERA_EXIT_CODE".into(), exit_code);
+        }
+
+        let shell = hook.shell.unwrap_or(self.get_load().params.is_shell());
+        let result = execute_command(&hook.command, shell, &dir, env_vars, &self.process_options(step))?;
+
+        let record = json!({
+            "name": hook.get_name(),
+            "step": step,
+            "command": hook.command.to_string(),
+            "exit_code": result.code(),
+        });
+        match self.get_ctx_mut()["hooks"].as_array_mut() {
+            Some(records) => records.push(record),
+            None => self.update_ctx("hooks", json!([record])),
+        }
+
+        // cancelled or timed out inlet and outlet hooks stop the run as the runner does
+        if !hook.step.is_final() {
+            self.check_termination(step, &result);
+            if result.termination.is_some() {
+                return Ok(());
+            }
+        }
+        if !result.success() {
+            return Err(CubteraError::Runner(format!(
+                "{} hook {} failed with {}",
+                capitalize_first(step),
+                hook.get_name(),
+                result.status
+            ))
+            .into());
+        }
+        debug!(target: "runner", "{} hook {} executed successfully", capitalize_first(step), hook.get_name());
+
+        Ok(())
+    }
+
+    // New output log for every run in unit temp folder, if capture_output param is enabled
+    fn init_output_log(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.get_load().params.is_capture_output() {
+            return Ok(());
+        }
+        let path = self.get_load().unit.temp_folder.join(OUTPUT_LOG_FILE);
+        std::fs::write(&path, "")?;
+        self.update_ctx("output_log", json!(path.to_string_lossy()));
+
+        Ok(())
+    }
+
+    fn output_capture(&self) -> Option<OutputCapture> {
+        let path = self.get_ctx().get("output_log")?.as_str()?;
+        Some(OutputCapture::new(
+            PathBuf::from(path),
+            self.get_load().params.is_strip_ansi(),
+        ))
+    }
+
+    fn process_options(&self, step: &str) -> ProcessOptions {
+        let params = &self.get_load().params;
+        ProcessOptions {
+            capture: self.output_capture(),
+            timeout: params.get_step_timeout(step),
+            grace_period: params.get_grace_period(),
+        }
+    }
+
+    // Saves cancellation or timeout of the step process to ctx
+    fn check_termination(&mut self, step: &str, result: &ProcessResult) {
+        if let Some(termination) = &result.termination {
+            warn!(target: "runner", "{} command was {}", capitalize_first(step), termination);
+            self.update_ctx(
+                "termination",
+                json!({
+                    "step": step,
+                    "reason": termination.reason(),
+                    "details": termination.to_string(),
+                }),
+            );
+            self.update_ctx("exit_code", json!(termination.exit_code()));
+        }
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.get_ctx().get("termination").is_some_and(|t| !t.is_null())
+    }
+
+    // Terminated run or non-zero exit code, plan with changes is not a failure
+    fn is_failed(&self) -> bool {
+        let ctx = self.get_ctx();
+        let exit_code = ctx.get("exit_code").and_then(|c| c.as_i64()).unwrap_or(0);
+        let plan_changes = ctx.get("plan_status").is_some_and(|s| s == "changes");
+        self.is_terminated() || (exit_code != 0 && !plan_changes)
+    }
+
+    // Dlog record with captured output and termination details of the run
+    fn build_dlog(&self, command: &str, exit_code: i32) -> Dlog {
+        let mut dlog = Dlog::build(self.get_load().unit.clone(), command.into(), exit_code);
+        if let Some(capture) = self.output_capture() {
+            dlog = dlog.with_output_log(&capture.path);
+        }
+        if let Some(details) = self.get_ctx().pointer("/termination/details").and_then(|d| d.as_str()) {
+            dlog = dlog.with_termination(details);
+        }
+        dlog
+    }
+
+    // Process env with runner command args, unit and dimension values (dim_env_vars param)
+    fn get_env_vars(&self) -> HashMap<String, String> {
+        let load = self.get_load();
+        let mut env_vars = std::env::vars().collect::<HashMap<String, String>>();
+        if load.params.is_dim_env_vars() {
+            env_vars.extend(env::unit_env_vars(
+                &load.unit,
+                &load.params.get_dim_env_prefix(),
+                load.params.get_dim_env_depth(),
+            ));
+        }
+        env_vars.insert("CUBTERA_RUNNER_CMD".into(), quote_args(&load.command));
+        env_vars
+    }
+
     fn update_ctx(&mut self, key: &str, value: Value) {
         let ctx = self.get_ctx_mut();
         ctx[key] = value;
@@ -138,33 +392,29 @@ pub trait Runner {
 
         let params = self.get_load().params.clone();
         let args = self.get_load().command.clone();
+        let shell = params.is_shell();
 
         let command = match step {
-            "inlet" => params.inlet_command,
-            "outlet" => params.outlet_command,
             "runner" => params
                 .runner_command
-                .and_then(|cmd| format!("{} {}", cmd, args.join(" ")).into())
-                .and_then(|cmd| {
-                    format!("{} {}", cmd, params.extra_args.unwrap_or_default()).into()
-                }),
+                .map(|cmd| runner_command_line(&cmd, &args, params.extra_args.as_ref(), shell))
+                .transpose()?,
             _ => None,
         };
 
-        if let Some::<String>(command) = command {
-            self.update_ctx(step, json!(format!("{}", &command)));
-            info!(target: "runner", "{} command: {}", capitalize_first(step), &command.blue());
+        if let Some(command) = command {
+            self.update_ctx(step, json!(command.to_string()));
+            info!(target: "runner", "{} command: {}", capitalize_first(step), command.blue());
 
-            let mut env_vars = std::env::vars().collect::<HashMap<String, String>>();
-            env_vars.insert("CUBTERA_RUNNER_CMD".into(), self.get_load().command.join(" "));
+            let env_vars = self.get_env_vars();
+            let result = execute_command(&command, shell, &dir, env_vars, &self.process_options(step))?;
 
-            let exit_code = execute_command(&command, &dir, env_vars)?;
-
-            self.update_ctx(&format!("{}_exit_code", step), json!(exit_code.code()));
-            if exit_code.success() {
+            self.update_ctx(&format!("{}_exit_code", step), json!(result.code()));
+            self.check_termination(step, &result);
+            if result.success() || result.termination.is_some() {
                 debug!(target: "runner", "{} command executed successfully", capitalize_first(step));
             } else {
-                exit_with_error(format!("{} command failed with {}", capitalize_first(step), exit_code));
+                return Err(CubteraError::Runner(format!("{} command failed with {}", capitalize_first(step), result.status)).into());
             }
         }
 
@@ -182,125 +432,164 @@ impl RunnerBuilder {
         RunnerBuilder { unit, command }
     }
 
-    pub fn build(&self) -> Box<dyn Runner> {
+    pub fn build(&self) -> CubteraResult<Box<dyn Runner>> {
+        let params = self.get_params()?;
+
+        let state_backend = get_state_backend(
+            &self.unit.manifest.unit_type,
+            self.unit.manifest.runner.as_ref(),
+            self.unit.manifest.state.as_ref(),
+            &self.unit.name,
+            &self.unit.get_unit_state_path(),
+        )?;
+
+        // state backends of units from manifest depends_on list, by unit name
+        let dependencies = self
+            .unit
+            .get_dependencies()?
+            .into_iter()
+            .map(|dep| {
+                let state_backend = get_state_backend(
+                    &dep.manifest.unit_type,
+                    dep.manifest.runner.as_ref(),
+                    dep.manifest.state.as_ref(),
+                    &dep.name,
+                    &dep.state_path,
+                )?;
+                Ok((dep.name, state_backend))
+            })
+            .collect::<CubteraResult<serde_json::Map<String, Value>>>()?;
+
+        let load = RunnerLoad {
+            unit: self.unit.clone(),
+            command: self.command.clone(),
+            params,
+            state_backend,
+            dependencies: Value::Object(dependencies),
+        };
+
+        // types unknown to cubtera are resolved with runner plugin registry: `plugin` param of the type in global config
+        let runner_type = match RunnerType::str_to_runner_type(&self.unit.manifest.unit_type) {
+            RunnerType::UNKNOWN if load.params.plugin.is_some() => RunnerType::PLUGIN,
+            runner_type => runner_type,
+        };
+        runner_create(runner_type, load)
+    }
+
+    // Process exit code of a plan with changes, for callers running the unit in a separate process
+    pub fn get_drift_exit_code(&self) -> CubteraResult<i32> {
+        Ok(self.get_params()?.get_drift_exit_code())
+    }
+
+    // Runner params of the unit type from global config, overridden by unit manifest runner params
+    fn get_params(&self) -> CubteraResult<params::RunnerParams> {
         let mut params = HashMap::new();
-        let mut state_type = "";
-        // let mut state_backend = Value::Null;
-
-        if let Some(runner) = &GLOBAL_CFG.runner {
-            // let config_runner = runner.get(&self.unit.manifest.unit_type);
-            if let Some(config_runner_params) = runner.get(&self.unit.manifest.unit_type) {
-                // read runner params from global config
-                params.extend(config_runner_params.clone());
-
-                // check if state type is defined in global config and overwrite default
-                if let Some(state) = config_runner_params.get("state_backend") {
-                    state_type = state;
-                }
-            }
+        let mut hooks = Vec::new();
+
+        if let Some(config_runner_params) = GLOBAL_CFG.get_runner_by_type(&self.unit.manifest.unit_type) {
+            // read runner params from global config
+            hooks.extend(get_hooks_value(&config_runner_params));
+            params.extend(config_runner_params);
         }
 
         if let Some(manifest_runner_params) = &self.unit.manifest.runner {
             // read runner params from unit manifest
             params.extend(manifest_runner_params.clone());
-
-            // check if state type is defined in unit manifest and overwrite global config
-            if let Some(state) = manifest_runner_params.get("state_backend") {
-                state_type = state;
-            }
+            // unit hooks are added after global ones instead of overwriting them
+            hooks.extend(get_hooks_value(manifest_runner_params));
         }
 
-        if state_type.is_empty() {
-            debug!(target: "runner", "State type is not defined in global config or unit manifest. Using default state type: 'local'");
-            state_type = "local";
-        }
+        params.insert("hooks".into(), Value::Array(hooks));
 
+        params::RunnerParams::init(params)
+    }
+}
 
-        let state_backend = GLOBAL_CFG
-            .clone()
-            .state
-            .and_then(|s| s.get(state_type).cloned())
-            .or(self.unit.manifest.state.clone())
-            .map_or_else(
-                ||
-                    // json!({
-                    //     "local": {
-                    //         "path": string_to_path("~/.cubtera/state/{{ org }}/{{ dim_tree }}/{{ unit_name }}.tfstate"),
-                    //     }
-                    // }),
-
-                    // TODO: remove after migration to new state backend config
-                    exit_with_error("State backend config is not defined in global config or unit manifest. \
-                        Check documentation about supported state backends".into()),
-
-                |state| {
-                    if state_type == "local" {
-                        json!({
-                            "local": {
-                                "path": string_to_path(state.get("path")
-                                    .unwrap_or_exit("Local backend path is not defined right in global config or unit manifest".into())),
-                            }
-                        })
-                    } else { json!({ state_type: state}) }
-            });
-
-            // .map(|state| json!({ state_type: state }))
-            // .unwrap_or(json!({
-            //     "local": {
-            //         "path": string_to_path("~/.cubtera/state/{{ org }}/{{ dim_tree }}/{{ unit_name }}.tfstate"),
-            //     }
-            // }));
-
-        // // check if state type is defined in global config
-        // if let Some(s) = GLOBAL_CFG.clone().state
-        //     .and_then(|s| s.get(state_type).cloned()){
-        //     state_backend = json!(s);
-        // }
-        //
-        // // check if state type is defined in unit manifest
-        // if let Some(state) = &self.unit.manifest.state {
-        //     state_backend = json!(state.clone());
-        // }
-        // // self.unit.manifest.state.clone()
-        // //     .map(|s| state_backend = json!(s));
-
-        // let state_backend = match backend {
-        //     None => {
-        //         debug!(target: "runner", "State backend config is not defined in global config or unit manifest. Using default.");
-        //         json!({
-        //             "local": {
-        //                 "path": string_to_path("~/.cubtera/state/{{ org }}/{{ dim_tree }}/{{ unit_name }}.tfstate"),
-        //             }
-        //         })
-        //     }
-        //     Some(backend) => json!({ state_type: backend }),
-        // };
-
-        // TODO: Move this to a separate function
-        // apply handlebars template to state_backend definition
-        let mut handlebars = handlebars::Handlebars::new();
-        handlebars.set_strict_mode(true);
-
-        // Add values for state_backend template rendering
-        let data = json!({
-            "org": &GLOBAL_CFG.org,
-            "unit_name": &self.unit.name,
-            "dim_tree": self.unit.get_unit_state_path(),
+// State backend of a unit: { "<state_type>": { <config> } } with applied template.
+// State type is defined by state_backend runner param in global config or unit manifest, "local" by default
+fn get_state_backend(
+    unit_type: &str,
+    manifest_runner_params: Option<&HashMap<String, Value>>,
+    manifest_state: Option<&HashMap<String, String>>,
+    unit_name: &str,
+    dim_tree: &str,
+) -> CubteraResult<Value> {
+    let config_runner_params = GLOBAL_CFG.get_runner_by_type(unit_type);
+    let state_type = manifest_runner_params
+        .and_then(|params| params.get("state_backend"))
+        .or(config_runner_params.as_ref().and_then(|params| params.get("state_backend")))
+        .and_then(Value::as_str)
+        .filter(|state_type| !state_type.is_empty())
+        .unwrap_or_else(|| {
+            debug!(target: "runner", "State type is not defined in global config or unit manifest. Using default state type: 'local'");
+            "local"
         });
 
-        let state_backend = apply_template_to_value(&state_backend, &handlebars, &data);
-        let params = params::RunnerParams::init(params);
-
-        let load = RunnerLoad {
-            unit: self.unit.clone(),
-            command: self.command.clone(),
-            params,
-            state_backend,
-        };
+    let state = GLOBAL_CFG
+        .state
+        .as_ref()
+        .and_then(|s| s.get(state_type).cloned())
+        .or(manifest_state.cloned())
+        // TODO: remove after migration to new state backend config
+        .or_error(CubteraError::Runner, "State backend config is not defined in global config or unit manifest. \
+            Check documentation about supported state backends".into())?;
+
+    let state_backend = if state_type == "local" {
+        json!({
+            "local": {
+                "path": string_to_path(state.get("path")
+                    .or_error(CubteraError::Runner, "Local backend path is not defined right in global config or unit manifest".into())?),
+            }
+        })
+    } else { json!({ state_type: state}) };
+
+    // TODO: Move this to a separate function
+    // apply handlebars template to state_backend definition
+    let mut handlebars = handlebars::Handlebars::new();
+    handlebars.set_strict_mode(true);
+
+    // Add values for state_backend template rendering
+    let data = json!({
+        "org": &GLOBAL_CFG.org,
+        "unit_name": unit_name,
+        "dim_tree": dim_tree,
+    });
+
+    Ok(apply_template_to_value(&state_backend, &handlebars, &data))
+}
 
-        let runner_type = RunnerType::str_to_runner_type(&self.unit.manifest.unit_type);
-        runner_create(runner_type, load)
+// Runner command with cli args and extra_args, one command line in shell mode
+fn runner_command_line(
+    command: &CommandLine,
+    args: &[String],
+    extra_args: Option<&CommandLine>,
+    shell: bool,
+) -> Result<CommandLine, String> {
+    if shell {
+        let line = [
+            command.shell_line(),
+            quote_args(args),
+            extra_args.map(CommandLine::shell_line).unwrap_or_default(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<String>>()
+        .join(" ");
+        return Ok(CommandLine::Line(line));
     }
+
+    let mut argv = command.argv()?;
+    argv.extend_from_slice(args);
+    argv.extend(extra_args.map(CommandLine::argv).transpose()?.unwrap_or_default());
+    Ok(CommandLine::Argv(argv))
+}
+
+fn get_hooks_value(runner_params: &HashMap<String, Value>) -> Vec<Value> {
+    runner_params
+        .get("hooks")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default()
 }
 
 fn apply_template_to_value(
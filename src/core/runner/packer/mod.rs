@@ -0,0 +1,293 @@
+// Packer runner implementation
+// Converts dims data files into *.auto.pkrvars.json with generated variable declarations,
+// runs packer init/validate/build and reads built artifacts from the manifest post-processor
+
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::path::PathBuf;
+use std::process::Command;
+use yansi::Paint;
+
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::process::run_process;
+
+const VARS_FILE: &str = "cubtera_vars.pkr.hcl";
+const DEFAULT_MANIFEST_FILE: &str = "packer-manifest.json";
+
+pub struct PackerRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for PackerRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        PackerRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn change_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        debug!(target: "packer runner", "Convert dims data files into packer format");
+
+        let temp_folder = &self.load.unit.temp_folder;
+
+        // cubtera_dim_<dim_type>.json and cubtera_ext.json files
+        let mut files = std::fs::read_dir(temp_folder)
+            .or_error(
+                CubteraError::Runner,
+                format!("Can't read unit temp folder: {:?}", temp_folder),
+            )?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|entry| entry.is_file())
+            .filter(|entry| entry.extension().unwrap_or_default() == "json")
+            .filter(|entry| {
+                let stem = entry.file_stem().unwrap_or_default().to_string_lossy();
+                stem.starts_with("cubtera_") && !stem.contains(".auto.pkrvars")
+            })
+            .collect::<Vec<PathBuf>>();
+        // read_dir order is not defined, sorted files keep cubtera_vars.pkr.hcl stable
+        files.sort();
+
+        if !files.is_empty() {
+            let mut keys = Vec::new();
+            for file in &files {
+                if let Some(Value::Object(obj)) = read_json_file(file)? {
+                    keys.extend(obj.into_iter().map(|(key, _)| key));
+                }
+            }
+            std::fs::write(temp_folder.join(VARS_FILE), variables_hcl(&keys))
+                .or_error(CubteraError::Runner, format!("Can't write {VARS_FILE}"))?;
+        }
+
+        // packer loads *.auto.pkrvars.json files of the template folder
+        for file in &files {
+            let new_file = file.with_file_name(format!(
+                "{}.auto.pkrvars.json",
+                file.file_stem().unwrap_or_default().to_string_lossy()
+            ));
+            std::fs::rename(file, new_file).or_error(
+                CubteraError::Runner,
+                format!("Can't rename file: {:?}", file),
+            )?;
+        }
+
+        self.update_ctx("change_files", json!("executed"));
+
+        Ok(())
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (packer_path, global_args) = self
+            .load
+            .params
+            .get_runner_program()?
+            .unwrap_or_else(|| (PathBuf::from("packer"), vec![]));
+
+        let (command, args) = match self.load.command.split_first() {
+            Some((command, args)) => (command.clone(), args.to_vec()),
+            None => ("build".to_string(), vec![]),
+        };
+
+        // build is preceded by init and validate, validate by init. CLI args are passed to the last command
+        let mut steps: Vec<Vec<String>> = match command.as_str() {
+            "build" => vec![
+                vec!["init".into(), ".".into()],
+                vec!["validate".into(), ".".into()],
+            ],
+            "validate" => vec![vec!["init".into(), ".".into()]],
+            _ => vec![],
+        };
+        let mut last_step = vec![command.clone()];
+        last_step.extend(args);
+        last_step.extend(self.load.params.get_extra_args()?);
+        if matches!(command.as_str(), "init" | "validate" | "build") {
+            last_step.push(".".into());
+        }
+        steps.push(last_step);
+
+        let env_vars = self.get_env_vars();
+        let mut exit_code = 0;
+        for step in steps {
+            let packer_args = [global_args.clone(), step].concat();
+            info!(target: "packer runner", "Command: {} {}",
+                packer_path.to_string_lossy().blue(),
+                packer_args.join(" ").blue(),
+            );
+
+            let mut packer_command = Command::new(&packer_path);
+            packer_command
+                .current_dir(&self.load.unit.temp_folder)
+                .args(&packer_args)
+                .envs(&env_vars)
+                .env("CHECKPOINT_DISABLE", "1");
+
+            let result = run_process(&mut packer_command, &self.process_options("runner"))
+                .or_error(
+                    CubteraError::Runner,
+                    format!(
+                        "Failed to run {:?} with args {:?}",
+                        packer_path, &packer_args
+                    ),
+                )?;
+            self.check_termination("runner", &result);
+            exit_code = result.code();
+            if exit_code != 0 {
+                break;
+            }
+        }
+
+        let artifacts = match command.as_str() {
+            "build" => self.read_artifacts(),
+            _ => vec![],
+        };
+        if !artifacts.is_empty() {
+            info!(target: "packer runner", "Built artifacts: {}",
+                artifacts.iter().map(|(name, id)| format!("{name}: {id}")).collect::<Vec<_>>().join(", ").blue(),
+            );
+        }
+
+        if GLOBAL_CFG.dlog_db.is_some() && command == "build" {
+            let _ = self
+                .build_dlog("build", exit_code)
+                .with_artifacts(artifacts.iter().cloned().collect())
+                .put(&GLOBAL_CFG.org)
+                .check_with_warn("Can't put dlog to DB");
+            info!(target: "packer runner", "Dlog data was saved");
+        }
+
+        let artifacts = artifacts
+            .into_iter()
+            .map(|(name, id)| (name, json!(id)))
+            .collect::<Map<String, Value>>();
+        self.update_ctx("artifacts", json!(artifacts));
+        self.update_ctx("exit_code", json!(exit_code));
+
+        if command == "build"
+            && exit_code == 0
+            && self.load.params.is_save_outputs()
+            && !artifacts.is_empty()
+        {
+            // images are already built, failed artifacts saving doesn't fail the run
+            let values = json!({ "artifacts": artifacts });
+            match self
+                .load
+                .unit
+                .save_outputs(values.clone())
+                .check_with_warn("Can't save artifacts to inventory")
+            {
+                Ok(dim) => {
+                    info!(target: "packer runner", "Artifacts were saved to dimension {}", dim.blue());
+                    self.update_ctx("outputs", json!({ "dim": dim, "values": values }));
+                }
+                Err(e) => self.update_ctx("outputs", json!({ "error": e.to_string() })),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PackerRunner {
+    // Artifacts of the last build from manifest post-processor output, by build name
+    fn read_artifacts(&self) -> Vec<(String, String)> {
+        let manifest_file = self
+            .load
+            .params
+            .manifest_file
+            .clone()
+            .unwrap_or(DEFAULT_MANIFEST_FILE.into());
+        let manifest_path = self.load.unit.temp_folder.join(&manifest_file);
+        if !manifest_path.exists() {
+            warn!(target: "packer runner", "Packer manifest {} is not found, add manifest post-processor to save artifacts", manifest_file.blue());
+            return vec![];
+        }
+
+        match read_json_file(&manifest_path) {
+            Ok(Some(manifest)) => manifest_artifacts(&manifest),
+            _ => {
+                warn!(target: "packer runner", "Can't read packer manifest {}", manifest_file.blue());
+                vec![]
+            }
+        }
+    }
+}
+
+// Variable declarations for dims data keys, type is inferred from values
+fn variables_hcl(keys: &[String]) -> String {
+    keys.iter()
+        .map(|key| {
+            format!(
+                r#"variable "{}" {{
+  description = "Generated by Cubtera"
+}}
+"#,
+                key
+            )
+        })
+        .collect()
+}
+
+// Manifest post-processor appends builds of every run, only builds of the last run are returned
+fn manifest_artifacts(manifest: &Value) -> Vec<(String, String)> {
+    let last_run = manifest.get("last_run_uuid").and_then(Value::as_str);
+    manifest
+        .get("builds")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|build| {
+            last_run.is_none() || build.get("packer_run_uuid").and_then(Value::as_str) == last_run
+        })
+        .filter_map(|build| {
+            let name = build.get("name").and_then(Value::as_str)?;
+            let artifact_id = build.get("artifact_id").and_then(Value::as_str)?;
+            Some((name.to_string(), artifact_id.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_artifacts() {
+        let manifest = json!({
+            "builds": [
+                { "name": "ubuntu", "artifact_id": "us-east-1:ami-old", "packer_run_uuid": "run-1" },
+                { "name": "ubuntu", "artifact_id": "us-east-1:ami-123,us-east-2:ami-456", "packer_run_uuid": "run-2" },
+                { "name": "docker", "artifact_id": "sha256:abc", "packer_run_uuid": "run-2" },
+            ],
+            "last_run_uuid": "run-2",
+        });
+        assert_eq!(
+            manifest_artifacts(&manifest),
+            [
+                (
+                    "ubuntu".to_string(),
+                    "us-east-1:ami-123,us-east-2:ami-456".to_string()
+                ),
+                ("docker".to_string(), "sha256:abc".to_string()),
+            ]
+        );
+        assert!(manifest_artifacts(&json!({})).is_empty());
+
+        assert_eq!(
+            variables_hcl(&["dim_dc_region".into()]),
+            "variable \"dim_dc_region\" {\n  description = \"Generated by Cubtera\"\n}\n"
+        );
+    }
+}
@@ -1,5 +1,8 @@
+use super::hooks::{Hook, HookStep};
 use crate::prelude::*;
+use crate::utils::command::CommandLine;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -9,35 +12,266 @@ pub struct RunnerParams {
     #[serde(default = "default_state_backend")]
     pub state_backend: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub runner_command: Option<String>,
+    pub runner_command: Option<CommandLine>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub extra_args: Option<String>,
+    pub extra_args: Option<CommandLine>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub inlet_command: Option<String>,
+    pub inlet_command: Option<CommandLine>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub outlet_command: Option<String>,
+    pub outlet_command: Option<CommandLine>,
+    #[serde(default = "default_shell")]
+    pub shell: String,
     #[serde(default = "default_lock_port")]
     pub lock_port: String,
+    #[serde(default = "default_lock_timeout")]
+    pub lock_timeout: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub versions_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archives_path: Option<String>,
+    #[serde(default = "default_offline")]
+    pub offline: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_file: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inlet_timeout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outlet_timeout: Option<String>,
+    #[serde(default = "default_grace_period")]
+    pub grace_period: String,
+    #[serde(default = "default_capture_output")]
+    pub capture_output: String,
+    #[serde(default = "default_strip_ansi")]
+    pub strip_ansi: String,
+    #[serde(default = "default_detailed_exitcode")]
+    pub detailed_exitcode: String,
+    #[serde(default = "default_drift_exit_code")]
+    pub drift_exit_code: String,
+    #[serde(default = "default_save_outputs")]
+    pub save_outputs: String,
+    #[serde(default = "default_dim_env_vars")]
+    pub dim_env_vars: String,
+    #[serde(default = "default_dim_env_prefix")]
+    pub dim_env_prefix: String,
+    #[serde(default = "default_dim_env_depth")]
+    pub dim_env_depth: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chart: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    #[serde(default = "default_engine")]
+    pub engine: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_tag: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_env: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub container_args: Option<CommandLine>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_dir: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stack: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub backend_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secrets_provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_file: Option<String>,
+    #[serde(default = "default_cli_config")]
+    pub cli_config: String,
+    #[serde(default = "default_plugin_cache_dir")]
+    pub plugin_cache_dir: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_installation: Option<Vec<Value>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<CommandLine>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<Hook>,
 }
 
 #[allow(dead_code)]
 impl RunnerParams {
-    pub fn init(params: HashMap<String, String>) -> Self {
-        let value =
-            serde_json::to_value(params).unwrap_or_exit("Failed to convert runner params".into());
-        serde_json::from_value::<RunnerParams>(value)
-            .unwrap_or_exit("Failed to convert runner params".into())
+    pub fn init(params: HashMap<String, Value>) -> CubteraResult<Self> {
+        // scalar params are kept as strings, e.g. `lock_timeout = 600` in config file
+        let value = params
+            .into_iter()
+            .map(|(key, value)| match value {
+                Value::Number(_) | Value::Bool(_) => (key, Value::String(value.to_string())),
+                _ => (key, value),
+            })
+            .collect::<serde_json::Map<String, Value>>();
+        serde_json::from_value::<RunnerParams>(Value::Object(value))
+            .or_error(CubteraError::Runner, "Failed to convert runner params".into())
     }
 
     pub fn get_params_hashmap(&self) -> HashMap<String, String> {
         let value = serde_json::to_value(self).unwrap_or_default();
-        serde_json::from_value::<HashMap<String, String>>(value).unwrap_or_default()
+        value
+            .as_object()
+            .map(|params| {
+                params
+                    .iter()
+                    .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Hooks of the step in execution order: legacy inlet/outlet command first,
+    // then hooks from global config and unit manifest enabled for runner command
+    pub fn get_hooks(&self, step: HookStep, runner_command: &str) -> Vec<Hook> {
+        let legacy = match step {
+            HookStep::Inlet => self.inlet_command.as_ref(),
+            HookStep::Outlet => self.outlet_command.as_ref(),
+            _ => None,
+        }
+        .map(|command| Hook::new(step.as_str(), command.clone(), step));
+
+        legacy
+            .into_iter()
+            .chain(
+                self.hooks
+                    .iter()
+                    .filter(|hook| hook.is_enabled_for(step, runner_command))
+                    .cloned(),
+            )
+            .collect()
     }
 
     pub fn get_lock_port(&self) -> u16 {
         self.lock_port.parse().unwrap_or(65432)
     }
 
+    pub fn get_lock_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.lock_timeout.parse().unwrap_or(1800))
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.offline.parse().unwrap_or(false)
+    }
+
+    // Timeout of inlet, runner or outlet step in seconds, on_failure and always hooks use outlet timeout
+    pub fn get_step_timeout(&self, step: &str) -> Option<std::time::Duration> {
+        let timeout = match step {
+            "inlet" => &self.inlet_timeout,
+            "outlet" | "on_failure" | "always" => &self.outlet_timeout,
+            _ => &self.timeout,
+        };
+        timeout
+            .as_ref()
+            .and_then(|t| t.parse().ok())
+            .map(std::time::Duration::from_secs)
+    }
+
+    pub fn get_grace_period(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.grace_period.parse().unwrap_or(60))
+    }
+
+    // Custom runner binary and its leading args from runner_command
+    pub fn get_runner_program(&self) -> CubteraResult<Option<(std::path::PathBuf, Vec<String>)>> {
+        let Some(command) = &self.runner_command else {
+            return Ok(None);
+        };
+        let mut argv = command
+            .argv()
+            .or_error(CubteraError::Runner, "Invalid runner_command".into())?
+            .into_iter();
+        let program = argv
+            .next()
+            .or_error(CubteraError::Runner, "Runner command is empty".into())?;
+        Ok(Some((string_to_path(&program), argv.collect())))
+    }
+
+    // Extra args split to argv, array value is passed as is
+    pub fn get_extra_args(&self) -> CubteraResult<Vec<String>> {
+        self.extra_args
+            .as_ref()
+            .map(CommandLine::argv)
+            .transpose()
+            .or_error(CubteraError::Runner, "Invalid extra_args".into())
+            .map(Option::unwrap_or_default)
+    }
+
+    // Runner and hook commands run with `/bin/sh -c`
+    pub fn is_shell(&self) -> bool {
+        self.shell.parse().unwrap_or(false)
+    }
+
+    pub fn is_capture_output(&self) -> bool {
+        self.capture_output.parse().unwrap_or(false)
+    }
+
+    pub fn is_strip_ansi(&self) -> bool {
+        self.strip_ansi.parse().unwrap_or(false)
+    }
+
+    pub fn is_detailed_exitcode(&self) -> bool {
+        self.detailed_exitcode.parse().unwrap_or(false)
+    }
+
+    pub fn get_drift_exit_code(&self) -> i32 {
+        self.drift_exit_code.parse().unwrap_or(2)
+    }
+
+    pub fn is_save_outputs(&self) -> bool {
+        self.save_outputs.parse().unwrap_or(false)
+    }
+
+    // Unit and dimension values are exported as env vars to runner and hook processes
+    pub fn is_dim_env_vars(&self) -> bool {
+        self.dim_env_vars.parse().unwrap_or(true)
+    }
+
+    pub fn get_dim_env_prefix(&self) -> String {
+        self.dim_env_prefix.clone()
+    }
+
+    // Nesting levels of dimension data flattened to separate env vars, deeper objects are json
+    pub fn get_dim_env_depth(&self) -> usize {
+        self.dim_env_depth.parse().unwrap_or(3)
+    }
+
+    // Terraform CLI config with plugin cache and provider installation is generated per run
+    pub fn is_cli_config(&self) -> bool {
+        self.cli_config.parse().unwrap_or(true)
+    }
+
+    // Container image with tag, image_tag overrides the tag of image param
+    pub fn get_image(&self) -> Option<String> {
+        let image = self.image.as_ref()?;
+        match &self.image_tag {
+            Some(tag) => {
+                // registry port (host:5000/name) is not a tag
+                let name = match image.rsplit_once(':') {
+                    Some((name, tag)) if !tag.contains('/') => name,
+                    _ => image,
+                };
+                Some(format!("{name}:{tag}"))
+            }
+            None => Some(image.clone()),
+        }
+    }
+
+    // Extra args of container engine `run` command, e.g. `--network host`
+    pub fn get_container_args(&self) -> CubteraResult<Vec<String>> {
+        self.container_args
+            .as_ref()
+            .map(CommandLine::argv)
+            .transpose()
+            .or_error(CubteraError::Runner, "Invalid container_args".into())
+            .map(Option::unwrap_or_default)
+    }
+
     pub fn get_version(&self) -> String {
         self.version.clone()
     }
@@ -47,10 +281,70 @@ impl RunnerParams {
     }
 }
 
+fn default_shell() -> String {
+    String::from("false")
+}
+
 fn default_lock_port() -> String {
     String::from("65432")
 }
 
+fn default_lock_timeout() -> String {
+    String::from("1800")
+}
+
+fn default_offline() -> String {
+    String::from("false")
+}
+
+fn default_grace_period() -> String {
+    String::from("60")
+}
+
+fn default_capture_output() -> String {
+    String::from("false")
+}
+
+fn default_strip_ansi() -> String {
+    String::from("false")
+}
+
+fn default_detailed_exitcode() -> String {
+    String::from("false")
+}
+
+fn default_drift_exit_code() -> String {
+    String::from("2")
+}
+
+fn default_save_outputs() -> String {
+    String::from("false")
+}
+
+fn default_dim_env_vars() -> String {
+    String::from("true")
+}
+
+fn default_dim_env_prefix() -> String {
+    String::from("CUBTERA")
+}
+
+fn default_dim_env_depth() -> String {
+    String::from("3")
+}
+
+fn default_cli_config() -> String {
+    String::from("true")
+}
+
+fn default_plugin_cache_dir() -> String {
+    String::from("~/.cubtera/plugin-cache")
+}
+
+fn default_engine() -> String {
+    String::from("docker")
+}
+
 fn default_version() -> String {
     String::from("latest")
 }
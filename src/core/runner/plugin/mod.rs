@@ -0,0 +1,248 @@
+// External runner plugin implementation
+// Runner types unknown to cubtera are resolved with `plugin` runner param (executable and its args).
+// For every lifecycle phase the plugin gets a JSON request on stdin and replies with JSON context updates on stdout,
+// plugin logs and tool output have to go to stderr.
+
+use log::{debug, info};
+use serde_json::{json, Map, Value};
+use std::fs::File;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use yansi::Paint;
+
+use super::hooks::HookStep;
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::process::run_process;
+
+const PROTOCOL_VERSION: u32 = 1;
+
+const REQUEST_FILE: &str = ".cubtera_plugin_request.json";
+const REPLY_FILE: &str = ".cubtera_plugin_reply.json";
+
+pub struct PluginRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for PluginRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        PluginRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn copy_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.load.unit.remove_temp_folder()?;
+        self.load.unit.copy_files()?;
+        self.update_ctx("copy_files", json!("executed"));
+        self.update_ctx("working_dir", json!(self.load.unit.temp_folder));
+
+        self.call_plugin("copy_files")
+    }
+
+    fn change_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.update_ctx("change_files", json!("executed"));
+        self.call_plugin("change_files")
+    }
+
+    // Render runs no user code: unit files are copied, plugin file phases are skipped
+    fn render(&mut self) -> Result<Value, Box<dyn std::error::Error>> {
+        self.load.unit.remove_temp_folder()?;
+        self.load.unit.copy_files()?;
+        self.update_ctx("copy_files", json!("executed"));
+        self.update_ctx("working_dir", json!(self.load.unit.temp_folder));
+        self.update_ctx("render", json!("executed"));
+
+        Ok(self.ctx.clone())
+    }
+
+    fn inlet(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.run_hooks(HookStep::Inlet)?;
+        self.call_plugin("inlet")
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.call_plugin("runner")
+    }
+
+    fn outlet(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        self.call_plugin("outlet")?;
+        self.run_hooks(HookStep::Outlet)
+    }
+}
+
+impl PluginRunner {
+    // Runs the plugin for the phase and applies its reply to ctx.
+    // Failed plugin fails the phase, except runner phase where its exit code is the unit exit code
+    fn call_plugin(&mut self, phase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let (program, args) = self.get_plugin_program()?;
+        let temp_folder = self.load.unit.temp_folder.clone();
+        std::fs::create_dir_all(&temp_folder).or_error(
+            CubteraError::Runner,
+            format!("Can't create unit temp folder: {:?}", temp_folder),
+        )?;
+
+        let request_file = temp_folder.join(REQUEST_FILE);
+        let reply_file = temp_folder.join(REPLY_FILE);
+        std::fs::write(
+            &request_file,
+            serde_json::to_string(&self.build_request(phase))?,
+        )
+        .or_error(
+            CubteraError::Runner,
+            format!("Can't write plugin request: {:?}", request_file),
+        )?;
+
+        debug!(target: "plugin runner", "Phase {}: {} {}", phase, program.to_string_lossy(), args.join(" "));
+        let mut command = Command::new(&program);
+        command
+            .current_dir(&temp_folder)
+            .args(&args)
+            .envs(self.get_env_vars())
+            .env("CUBTERA_PLUGIN_PHASE", phase)
+            .stdin(File::open(&request_file)?)
+            .stdout(Stdio::from(File::create(&reply_file)?));
+
+        // stdout is the reply, so plugin output is not captured to the output log.
+        // copy_files and change_files phases use outlet timeout
+        let step = match phase {
+            "inlet" | "runner" => phase,
+            _ => "outlet",
+        };
+        let mut options = self.process_options(step);
+        options.capture = None;
+
+        let result = run_process(&mut command, &options).or_error(
+            CubteraError::Runner,
+            format!("Failed to run plugin {:?} with args {:?}", program, &args),
+        );
+        let reply = std::fs::read_to_string(&reply_file).unwrap_or_default();
+        let _ = std::fs::remove_file(&request_file);
+        let _ = std::fs::remove_file(&reply_file);
+        let result = result?;
+        self.check_termination(phase, &result);
+
+        let reply = parse_reply(&reply).or_error(
+            CubteraError::Runner,
+            format!("Invalid plugin reply in {phase} phase, JSON object is expected on stdout"),
+        )?;
+        for (key, value) in &reply {
+            self.update_ctx(key, value.clone());
+        }
+
+        if phase == "runner" {
+            if !reply.contains_key("exit_code") {
+                self.update_ctx("exit_code", json!(result.code()));
+            }
+            info!(target: "plugin runner", "Plugin {} finished with exit code {}",
+                program.to_string_lossy().blue(),
+                self.get_ctx()["exit_code"],
+            );
+        } else if !result.success() {
+            return Err(CubteraError::Runner(format!(
+                "Plugin {phase} phase failed with exit code {}",
+                result.code()
+            ))
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn get_plugin_program(&self) -> CubteraResult<(PathBuf, Vec<String>)> {
+        let mut argv = self
+            .load
+            .params
+            .plugin
+            .as_ref()
+            .or_error(CubteraError::Runner, "Runner plugin is not defined".into())?
+            .argv()
+            .or_error(CubteraError::Runner, "Invalid runner plugin command".into())?
+            .into_iter();
+        let program = argv.next().or_error(
+            CubteraError::Runner,
+            "Runner plugin command is empty".into(),
+        )?;
+        Ok((string_to_path(&program), argv.collect()))
+    }
+
+    fn build_request(&self, phase: &str) -> Value {
+        let unit = &self.load.unit;
+        let dims = unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| {
+                json!({
+                    "type": dim.dim_type,
+                    "name": dim.dim_name,
+                    "data": dim.get_dim_data(),
+                })
+            })
+            .collect::<Vec<Value>>();
+        let extensions = unit
+            .extensions
+            .iter()
+            .filter_map(|ext| ext.split_once(':'))
+            .map(|(ext_type, ext_name)| (ext_type.to_string(), json!(ext_name)))
+            .collect::<Map<String, Value>>();
+
+        json!({
+            "protocol_version": PROTOCOL_VERSION,
+            "phase": phase,
+            "org": &GLOBAL_CFG.org,
+            "unit": {
+                "name": unit.name,
+                "type": unit.manifest.unit_type,
+                "dim_tree": unit.get_unit_state_path(),
+                "manifest": unit.manifest,
+                "extensions": extensions,
+            },
+            "dims": dims,
+            "params": self.load.params,
+            "state_backend": self.load.state_backend,
+            "dependencies": self.load.dependencies,
+            "command": self.load.command,
+            "temp_folder": unit.temp_folder,
+            "ctx": self.ctx,
+        })
+    }
+}
+
+// Empty reply means no ctx updates
+fn parse_reply(reply: &str) -> Option<Map<String, Value>> {
+    if reply.trim().is_empty() {
+        return Some(Map::new());
+    }
+    match serde_json::from_str(reply) {
+        Ok(Value::Object(map)) => Some(map),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        assert!(parse_reply(" \n").unwrap().is_empty());
+        assert_eq!(
+            parse_reply(r#"{"exit_code": 2, "stack": "dev"}"#).unwrap()["stack"],
+            "dev"
+        );
+        assert!(parse_reply("[1, 2]").is_none());
+        assert!(parse_reply("applying...\n{}").is_none());
+    }
+}
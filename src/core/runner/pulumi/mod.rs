@@ -0,0 +1,492 @@
+// Pulumi runner implementation
+// Maps the dim tree to a stack, generates stack config with dimension values
+// and runs pulumi with the backend from the templated state_backend
+
+use log::{debug, info, warn};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use yansi::Paint;
+
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::process::run_process;
+
+const DEFAULT_STACK: &str = "{{ dim_tree }}";
+const CONFIG_NAMESPACE: &str = "cubtera";
+// pulumi commands with --stack flag
+const STACK_COMMANDS: &[&str] = &[
+    "up", "preview", "destroy", "refresh", "import", "stack", "config", "cancel", "watch", "state",
+];
+
+pub struct PulumiRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for PulumiRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        PulumiRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn change_files(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let stack = self.get_stack()?;
+        let file_name = format!("Pulumi.{stack}.yaml");
+        let file_path = self.load.unit.temp_folder.join(&file_name);
+        let existing = match file_path.exists() {
+            true => Some(std::fs::read_to_string(&file_path).or_error(
+                CubteraError::Runner,
+                format!("Can't read stack config: {:?}", file_path),
+            )?),
+            false => None,
+        };
+        if existing.is_some() {
+            debug!(target: "pulumi runner", "Generated config is merged into stack config {} from unit", file_name);
+        }
+
+        // json is valid yaml
+        let stack_config = merge_stack_config(existing.as_deref(), self.get_stack_config())?;
+        std::fs::write(&file_path, serde_json::to_string_pretty(&stack_config)?).or_error(
+            CubteraError::Runner,
+            format!("Can't write stack config: {:?}", file_path),
+        )?;
+        debug!(target: "pulumi runner", "Stack config was saved to {}", file_name);
+
+        self.update_ctx("stack", json!(stack));
+        self.update_ctx("stack_config", json!(file_name));
+        self.update_ctx("change_files", json!("executed"));
+
+        Ok(())
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (pulumi_path, global_args) = self
+            .load
+            .params
+            .get_runner_program()?
+            .unwrap_or_else(|| (PathBuf::from("pulumi"), vec![]));
+        let stack = self.get_stack()?;
+        let env_vars = self.get_env_pulumi_vars()?;
+
+        // stack is created on the first run
+        let mut select_args = global_args.clone();
+        select_args.extend([
+            "stack".into(),
+            "select".into(),
+            "--create".into(),
+            "--non-interactive".into(),
+            stack.clone(),
+        ]);
+        if let Some(secrets_provider) = &self.load.params.secrets_provider {
+            select_args.extend(["--secrets-provider".into(), secrets_provider.clone()]);
+        }
+        let result = self.run_pulumi(&pulumi_path, &select_args, &env_vars)?;
+        if !result.success() {
+            warn!(target: "pulumi runner", "Can't select stack {}", stack.blue());
+            self.update_ctx("exit_code", json!(result.code()));
+            return Ok(());
+        }
+
+        let (command, args) = match self.load.command.split_first() {
+            Some((command, args)) => (command.clone(), args.to_vec()),
+            None => ("preview".to_string(), vec![]),
+        };
+        let mut pulumi_args = global_args;
+        pulumi_args.push(command.clone());
+        pulumi_args.extend(args);
+        if STACK_COMMANDS.contains(&command.as_str()) {
+            pulumi_args.extend(["--stack".into(), stack.clone()]);
+        }
+        pulumi_args.push("--non-interactive".into());
+        pulumi_args.extend(self.load.params.get_extra_args()?);
+
+        info!(target: "pulumi runner", "Stack: {} | Backend: {}",
+            stack.blue(),
+            env_vars["PULUMI_BACKEND_URL"].blue(),
+        );
+        info!(target: "pulumi runner", "Command: {} {}",
+            pulumi_path.to_string_lossy().blue(),
+            pulumi_args.join(" ").blue(),
+        );
+
+        let result = self.run_pulumi(&pulumi_path, &pulumi_args, &env_vars)?;
+        let exit_code = result.code();
+
+        let dlog_command = GLOBAL_CFG
+            .dlog_db
+            .clone()
+            .and(matches!(command.as_str(), "up" | "destroy").then_some(command.as_str()));
+
+        if let Some(dlog_command) = dlog_command {
+            let _ = self
+                .build_dlog(dlog_command, exit_code)
+                .put(&GLOBAL_CFG.org)
+                .check_with_warn("Can't put dlog to DB");
+            info!(target: "pulumi runner", "Dlog data was saved");
+        }
+
+        self.update_ctx("stack", json!(stack));
+        self.update_ctx("exit_code", json!(exit_code));
+
+        // stack is already updated, failed outputs saving doesn't fail the run
+        if command == "up" && exit_code == 0 && self.load.params.is_save_outputs() {
+            if let Err(e) = self
+                .save_outputs(&pulumi_path, &stack, env_vars)
+                .check_with_warn("Can't save outputs to inventory")
+            {
+                self.update_ctx("outputs", json!({ "error": e.to_string() }));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PulumiRunner {
+    fn run_pulumi(
+        &mut self,
+        pulumi_path: &Path,
+        args: &[String],
+        env_vars: &HashMap<String, String>,
+    ) -> CubteraResult<crate::utils::process::ProcessResult> {
+        let mut pulumi_command = Command::new(pulumi_path);
+        pulumi_command
+            .current_dir(&self.load.unit.temp_folder)
+            .args(args)
+            .envs(env_vars);
+
+        let result = run_process(&mut pulumi_command, &self.process_options("runner")).or_error(
+            CubteraError::Runner,
+            format!("Failed to run {:?} with args {:?}", pulumi_path, args),
+        )?;
+        self.check_termination("runner", &result);
+        Ok(result)
+    }
+
+    fn render_template(&self, template: &str) -> CubteraResult<String> {
+        let mut handlebars = handlebars::Handlebars::new();
+        handlebars.set_strict_mode(true);
+
+        let dims = self
+            .load
+            .unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| (dim.dim_type.clone(), json!(dim.dim_name)))
+            .collect::<Map<String, Value>>();
+        let data = json!({
+            "org": &GLOBAL_CFG.org,
+            "unit_name": &self.load.unit.name,
+            "dim_tree": self.load.unit.get_unit_state_path(),
+            "dims": dims,
+        });
+
+        handlebars.render_template(template, &data).or_error(
+            CubteraError::Runner,
+            format!("Failed to render pulumi runner template: {template}"),
+        )
+    }
+
+    fn get_stack(&self) -> CubteraResult<String> {
+        let template = self
+            .load
+            .params
+            .stack
+            .clone()
+            .unwrap_or(DEFAULT_STACK.into());
+        Ok(to_stack_name(&self.render_template(&template)?))
+    }
+
+    // Stack config in cubtera namespace: org, unit_name, dim_tree, data of unit dims with parents by dim type
+    // and extension names, e.g. `new pulumi.Config("cubtera").requireObject("dc")`
+    fn get_stack_config(&self) -> Map<String, Value> {
+        let unit = &self.load.unit;
+        let mut config = Map::new();
+        let mut set = |key: &str, value: Value| {
+            config.insert(format!("{CONFIG_NAMESPACE}:{key}"), value);
+        };
+        set("org", json!(GLOBAL_CFG.org));
+        set("unit_name", json!(unit.name));
+        set("dim_tree", json!(unit.get_unit_state_path()));
+        for dim in unit.get_all_dims() {
+            set(&dim.dim_type, dim.get_dim_data());
+        }
+        let ext = unit
+            .extensions
+            .iter()
+            .filter_map(|ext| ext.split_once(':'))
+            .map(|(ext_type, ext_name)| (ext_type.to_string(), json!(ext_name)))
+            .collect::<Map<String, Value>>();
+        set("ext", Value::Object(ext));
+        config
+    }
+
+    // Process env with PULUMI_BACKEND_URL from backend_url param or unit state backend
+    fn get_env_pulumi_vars(&self) -> CubteraResult<HashMap<String, String>> {
+        let mut env_vars = self.get_env_vars();
+        let (backend_url, backend_env) = match &self.load.params.backend_url {
+            Some(template) => (self.render_template(template)?, vec![]),
+            None => backend_url(
+                &self.load.state_backend,
+                &string_to_path(&GLOBAL_CFG.workspace_path),
+            )
+            .or_error(
+                CubteraError::Runner,
+                format!(
+                    "Can't convert state backend {} to pulumi backend. Set backend_url runner param",
+                    self.load.state_backend
+                ),
+            )?,
+        };
+
+        if let Some(dir) = backend_url.strip_prefix("file://") {
+            std::fs::create_dir_all(dir).or_error(
+                CubteraError::Runner,
+                format!("Can't create local backend folder: {dir}"),
+            )?;
+        }
+
+        env_vars.extend(backend_env);
+        env_vars.insert("PULUMI_BACKEND_URL".into(), backend_url);
+        env_vars.insert("PULUMI_SKIP_UPDATE_CHECK".into(), "true".into());
+        Ok(env_vars)
+    }
+
+    // Saves stack outputs after up to the inventory of the unit primary dimension
+    fn save_outputs(
+        &mut self,
+        pulumi_path: &Path,
+        stack: &str,
+        env_vars: HashMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let output = Command::new(pulumi_path)
+            .current_dir(&self.load.unit.temp_folder)
+            .args([
+                "stack",
+                "output",
+                "--json",
+                "--non-interactive",
+                "--stack",
+                stack,
+            ])
+            .envs(env_vars)
+            .stdout(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to read outputs with stack output --json: {}",
+                output.status
+            )
+            .into());
+        }
+
+        // secret outputs are shown as "[secret]" without --show-secrets
+        let mut secrets = Vec::new();
+        let values = serde_json::from_slice::<Value>(&output.stdout)?
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, value)| {
+                if value == "[secret]" {
+                    secrets.push(name.clone());
+                    return None;
+                }
+                Some((name.clone(), value.clone()))
+            })
+            .collect::<Map<String, Value>>();
+        if !secrets.is_empty() {
+            warn!(target: "pulumi runner", "Secret outputs are not saved to inventory: {}", secrets.join(", ").yellow());
+        }
+
+        let values = Value::Object(values);
+        let dim = self.load.unit.save_outputs(values.clone())?;
+        info!(target: "pulumi runner", "Outputs were saved to dimension {}", dim.blue());
+        self.update_ctx("outputs", json!({ "dim": dim, "values": values }));
+
+        Ok(())
+    }
+}
+
+// Stack name from dim tree: "dome:stg/env:stg1/dc:stg1-use2" => "dome-stg.env-stg1.dc-stg1-use2",
+// pulumi allows only alphanumerics, hyphens, underscores and periods
+fn to_stack_name(name: &str) -> String {
+    name.trim_matches('/')
+        .chars()
+        .map(|c| match c {
+            '/' => '.',
+            c if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' => c,
+            _ => '-',
+        })
+        .collect()
+}
+
+// Stack config file from unit with generated keys added to its config map,
+// other config keys (provider config, secure values) and top level keys (secrets provider) are kept
+fn merge_stack_config(
+    existing: Option<&str>,
+    generated: Map<String, Value>,
+) -> CubteraResult<Value> {
+    let mut stack_config = match existing.filter(|content| !content.trim().is_empty()) {
+        Some(content) => config::Config::builder()
+            .add_source(config::File::from_str(content, config::FileFormat::Yaml))
+            .build()
+            .and_then(|config| config.try_deserialize::<Map<String, Value>>())
+            .or_error(
+                CubteraError::Runner,
+                "Invalid stack config from unit".into(),
+            )?,
+        None => Map::new(),
+    };
+
+    let config = stack_config.entry("config").or_insert_with(|| json!({}));
+    if !config.is_object() {
+        *config = json!({});
+    }
+    if let Some(config) = config.as_object_mut() {
+        config.extend(generated);
+    }
+    Ok(Value::Object(stack_config))
+}
+
+// Pulumi backend url and env vars from unit state backend, file name of the state (e.g. unit.tfstate) is dropped:
+// pulumi keeps stacks of all projects in a backend folder. Relative local path is resolved from workspace folder
+fn backend_url(state_backend: &Value, workspace: &Path) -> Option<(String, Vec<(String, String)>)> {
+    let (state_type, config) = state_backend.as_object()?.iter().next()?;
+    let get = |key: &str| config.get(key).and_then(Value::as_str);
+    let folder = |path: &str| {
+        let path = Path::new(path);
+        let folder = match path.extension() {
+            Some(_) => path.parent().unwrap_or(path),
+            None => path,
+        };
+        folder.to_string_lossy().trim_matches('/').to_string()
+    };
+
+    match state_type.as_str() {
+        "local" => {
+            let path = workspace.join(string_to_path(get("path")?));
+            Some((
+                format!("file:///{}", folder(&path.to_string_lossy())),
+                vec![],
+            ))
+        }
+        "s3" => {
+            let mut url = format!(
+                "s3://{}/{}",
+                get("bucket")?,
+                folder(get("key").unwrap_or_default())
+            );
+            if let Some(region) = get("region") {
+                url.push_str(&format!("?region={region}"));
+            }
+            Some((url, vec![]))
+        }
+        "gcs" => Some((
+            format!(
+                "gs://{}/{}",
+                get("bucket")?,
+                folder(get("prefix").unwrap_or_default())
+            ),
+            vec![],
+        )),
+        "azurerm" => Some((
+            format!(
+                "azblob://{}/{}",
+                get("container_name")?,
+                folder(get("key").unwrap_or_default())
+            ),
+            vec![(
+                "AZURE_STORAGE_ACCOUNT".into(),
+                get("storage_account_name")?.into(),
+            )],
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_stack_name() {
+        assert_eq!(
+            to_stack_name("dome:stg/env:stg1/dc:stg1-use2"),
+            "dome-stg.env-stg1.dc-stg1-use2"
+        );
+        assert_eq!(to_stack_name("app {dc}"), "app--dc-");
+    }
+
+    #[test]
+    fn test_merge_stack_config() {
+        let existing = "secretsprovider: awskms://alias/pulumi\nencryptedkey: AbC==\nconfig:\n  aws:region: us-east-2\n  app:dbPassword:\n    secure: v1:XyZ\n  cubtera:dc: old\n";
+        let generated = json!({ "cubtera:dc": { "name": "stg1-use2" } });
+        let merged =
+            merge_stack_config(Some(existing), generated.as_object().unwrap().clone()).unwrap();
+        assert_eq!(
+            merged,
+            json!({
+                "secretsprovider": "awskms://alias/pulumi",
+                "encryptedkey": "AbC==",
+                "config": {
+                    "aws:region": "us-east-2",
+                    "app:dbPassword": { "secure": "v1:XyZ" },
+                    "cubtera:dc": { "name": "stg1-use2" },
+                },
+            })
+        );
+
+        let generated = json!({ "cubtera:org": "cubtera" });
+        assert_eq!(
+            merge_stack_config(None, generated.as_object().unwrap().clone()).unwrap(),
+            json!({ "config": { "cubtera:org": "cubtera" } })
+        );
+        assert!(merge_stack_config(Some("config: [1"), Map::new()).is_err());
+    }
+
+    #[test]
+    fn test_backend_url() {
+        let local = json!({ "local": { "path": "/state/dc:stg1/app.tfstate" } });
+        let workspace = Path::new("/work");
+        assert_eq!(
+            backend_url(&local, workspace).unwrap().0,
+            "file:///state/dc:stg1"
+        );
+        let relative = json!({ "local": { "path": "state/dc:stg1/app.tfstate" } });
+        assert_eq!(
+            backend_url(&relative, workspace).unwrap().0,
+            "file:///work/state/dc:stg1"
+        );
+
+        let s3 = json!({ "s3": { "bucket": "b", "key": "org/dc:stg1/app.tfstate", "region": "us-east-2" } });
+        assert_eq!(
+            backend_url(&s3, workspace).unwrap().0,
+            "s3://b/org/dc:stg1?region=us-east-2"
+        );
+
+        let azure = json!({ "azurerm": { "container_name": "c", "storage_account_name": "sa", "key": "app" } });
+        assert_eq!(
+            backend_url(&azure, workspace).unwrap(),
+            (
+                "azblob://c/app".to_string(),
+                vec![("AZURE_STORAGE_ACCOUNT".to_string(), "sa".to_string())]
+            )
+        );
+
+        assert!(backend_url(&json!({ "consul": { "path": "x" } }), workspace).is_none());
+    }
+}
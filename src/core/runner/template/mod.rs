@@ -0,0 +1,293 @@
+// Template runner implementation
+// Renders every *.hbs file of the unit with handlebars and dimension data,
+// rendered files are written to the unit temp folder or configured output_dir
+
+use log::{debug, info};
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use yansi::Paint;
+
+use super::{Runner, RunnerLoad};
+use crate::prelude::*;
+use crate::utils::snapshot::FolderSnapshot;
+
+const TEMPLATE_EXTENSION: &str = "hbs";
+
+pub struct TemplateRunner {
+    load: RunnerLoad,
+    ctx: Value,
+}
+
+impl Runner for TemplateRunner {
+    fn new(load: RunnerLoad) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        TemplateRunner { load, ctx }
+    }
+
+    fn get_load(&self) -> &RunnerLoad {
+        &self.load
+    }
+
+    fn get_ctx(&self) -> &Value {
+        &self.ctx
+    }
+
+    fn get_ctx_mut(&mut self) -> &mut Value {
+        &mut self.ctx
+    }
+
+    fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let command = self.load.command.first().cloned().unwrap_or("write".into());
+        let write = match command.as_str() {
+            "write" | "apply" => true,
+            "diff" | "plan" => false,
+            _ => {
+                return Err(Box::new(CubteraError::Runner(format!(
+                    "Unknown template runner command: {command}. Use write (apply) or diff (plan)"
+                ))))
+            }
+        };
+
+        let data = self.get_template_data();
+        let output_dir = self.get_output_dir(&data)?;
+        let rendered = render_templates(&self.load.unit.temp_folder, &data)?;
+        info!(target: "template runner", "Rendered {} template(s) for {}",
+            rendered.len(),
+            output_dir.to_string_lossy().blue(),
+        );
+
+        let existing = FolderSnapshot::from_files(rendered.iter().filter_map(|(path, _)| {
+            std::fs::read(output_dir.join(path))
+                .ok()
+                .map(|content| (path.clone(), content))
+        }));
+        let new = FolderSnapshot::from_files(
+            rendered
+                .iter()
+                .map(|(path, content)| (path.clone(), content.clone().into_bytes())),
+        );
+        let diff = existing.diff(&new);
+
+        let mut exit_code = 0;
+        if write {
+            for (path, content) in &rendered {
+                let file = output_dir.join(path);
+                if let Some(parent) = file.parent() {
+                    std::fs::create_dir_all(parent).or_error(
+                        CubteraError::Runner,
+                        format!("Can't create folder: {:?}", parent),
+                    )?;
+                }
+                std::fs::write(&file, content).or_error(
+                    CubteraError::Runner,
+                    format!("Can't write rendered file: {:?}", file),
+                )?;
+                debug!(target: "template runner", "Rendered file: {:?}", file);
+            }
+        } else {
+            if diff.is_empty() {
+                info!(target: "template runner", "No changes. Rendered files are up to date");
+            }
+            for line in &diff {
+                let line = match line.trim_start().chars().next() {
+                    Some('+') => line.green().to_string(),
+                    Some('-') => line.red().to_string(),
+                    Some('~') => line.yellow().to_string(),
+                    _ => line.clone(),
+                };
+                info!(target: "template runner", "{line}");
+            }
+            // same plan status and drift exit code params as tf plan, changes are not a failure
+            let plan_status = match diff.is_empty() {
+                true => "no_changes",
+                false => "changes",
+            };
+            self.update_ctx("plan_status", json!(plan_status));
+            if !diff.is_empty() && self.load.params.is_detailed_exitcode() {
+                exit_code = self.load.params.get_drift_exit_code();
+            }
+        }
+
+        // changed or new files, not their changed lines
+        let changes = diff.iter().filter(|line| !line.starts_with(' ')).count();
+        self.update_ctx("output_dir", json!(output_dir));
+        self.update_ctx(
+            "rendered",
+            json!(rendered.iter().map(|(path, _)| path).collect::<Vec<_>>()),
+        );
+        self.update_ctx("changes", json!(changes));
+        self.update_ctx("diff", json!(diff));
+        self.update_ctx("exit_code", json!(exit_code));
+
+        Ok(())
+    }
+}
+
+impl TemplateRunner {
+    // Render context: org, unit_name, dim_tree, full data of unit dims with parents and extensions names
+    fn get_template_data(&self) -> Value {
+        let dims = self
+            .load
+            .unit
+            .get_all_dims()
+            .iter()
+            .map(|dim| (dim.dim_type.clone(), dim.get_dim_data()))
+            .collect::<Map<String, Value>>();
+        let ext = self
+            .load
+            .unit
+            .extensions
+            .iter()
+            .filter_map(|ext| ext.split_once(':'))
+            .map(|(ext_type, ext_name)| (ext_type.to_string(), json!(ext_name)))
+            .collect::<Map<String, Value>>();
+
+        json!({
+            "org": &GLOBAL_CFG.org,
+            "unit_name": &self.load.unit.name,
+            "dim_tree": self.load.unit.get_unit_state_path(),
+            "dims": dims,
+            "ext": ext,
+        })
+    }
+
+    // output_dir param is a template too, relative path is resolved from the workspace folder
+    fn get_output_dir(&self, data: &Value) -> CubteraResult<PathBuf> {
+        let Some(template) = &self.load.params.output_dir else {
+            return Ok(self.load.unit.temp_folder.clone());
+        };
+        let output_dir = new_handlebars().render_template(template, data).or_error(
+            CubteraError::Runner,
+            format!("Failed to render template runner output_dir: {template}"),
+        )?;
+        let output_dir = string_to_path(&output_dir);
+        Ok(match output_dir.is_absolute() {
+            true => output_dir,
+            false => string_to_path(&GLOBAL_CFG.workspace_path).join(output_dir),
+        })
+    }
+}
+
+// Strict mode fails on missing values, html escaping is disabled for config files
+fn new_handlebars() -> handlebars::Handlebars<'static> {
+    let mut handlebars = handlebars::Handlebars::new();
+    handlebars.set_strict_mode(true);
+    handlebars.register_escape_fn(handlebars::no_escape);
+    handlebars
+}
+
+// Renders all *.hbs files of the folder (symlinks are not followed) to (relative path without .hbs, content).
+// Files with "_" prefix are registered as partials by name without prefix and extension, e.g. {{> labels}} for _labels.hbs
+fn render_templates(dir: &Path, data: &Value) -> CubteraResult<Vec<(String, String)>> {
+    let mut templates = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().unwrap_or_default() == TEMPLATE_EXTENSION)
+        .collect::<Vec<PathBuf>>();
+    templates.sort();
+
+    let mut handlebars = new_handlebars();
+    let mut files = Vec::new();
+    for path in templates {
+        let content = std::fs::read_to_string(&path).or_error(
+            CubteraError::Runner,
+            format!("Can't read template: {:?}", path),
+        )?;
+        let stem = path
+            .file_stem()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        match stem.strip_prefix('_') {
+            Some(partial) => handlebars.register_partial(partial, content).or_error(
+                CubteraError::Runner,
+                format!("Invalid partial template: {:?}", path),
+            )?,
+            None => files.push((path.with_extension(""), content)),
+        }
+    }
+
+    files
+        .into_iter()
+        .map(|(path, content)| {
+            let rendered = handlebars.render_template(&content, data).or_error(
+                CubteraError::Runner,
+                format!("Failed to render template: {:?}.{TEMPLATE_EXTENSION}", path),
+            )?;
+            let path = path
+                .strip_prefix(dir)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .to_string();
+            Ok((path, rendered))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_render_templates() {
+        let dir = tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("k8s")).unwrap();
+        std::fs::write(
+            dir.path().join("k8s/deploy.yaml.hbs"),
+            "name: {{ unit_name }}\nregion: {{ dims.dc.region }}\nenv: {{> labels }}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("_labels.hbs"), "<{{ dims.env.name }}>").unwrap();
+        std::fs::write(dir.path().join("main.sh"), "echo {{ unit_name }}").unwrap();
+
+        let data = json!({
+            "unit_name": "app",
+            "dims": { "dc": { "region": "us-east-2" }, "env": { "name": "stg1" } },
+        });
+        let rendered = render_templates(dir.path(), &data).unwrap();
+        assert_eq!(
+            rendered,
+            [(
+                "k8s/deploy.yaml".to_string(),
+                "name: app\nregion: us-east-2\nenv: <stg1>\n".to_string()
+            )]
+        );
+
+        std::fs::write(dir.path().join("bad.hbs"), "{{ dims.missing.key }}").unwrap();
+        assert!(render_templates(dir.path(), &data).is_err());
+    }
+
+    #[test]
+    fn test_diff_with_changes_is_success() {
+        let dir = tempdir().unwrap();
+        let unit_folder = dir.path().join("unit");
+        let output_dir = dir.path().join("out");
+        std::fs::create_dir_all(&unit_folder).unwrap();
+        std::fs::write(unit_folder.join("app.conf.hbs"), "name: {{ unit_name }}\n").unwrap();
+
+        let params = serde_json::from_value(json!({
+            "output_dir": output_dir.to_string_lossy(),
+            "detailed_exitcode": "true",
+            "drift_exit_code": "3",
+            "dim_env_vars": "false",
+        }))
+        .unwrap();
+        let load = RunnerLoad {
+            unit: Unit::for_test("app", "template", &unit_folder, &dir.path().join("temp")),
+            command: vec!["diff".into()],
+            params: super::super::params::RunnerParams::init(params).unwrap(),
+            state_backend: Value::Null,
+            dependencies: Value::Null,
+        };
+
+        let mut runner = <TemplateRunner as Runner>::new(load);
+        let ctx = runner.run().unwrap();
+        assert_eq!(ctx["plan_status"], "changes");
+        assert_eq!(ctx["exit_code"], 3);
+        assert_eq!(ctx["run_status"], "success");
+        assert!(!output_dir.join("app.conf").exists());
+    }
+}
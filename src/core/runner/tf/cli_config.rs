@@ -0,0 +1,147 @@
+// Terraform CLI config generated per run (TF_CLI_CONFIG_FILE): shared plugin cache,
+// plugins folder as filesystem mirror and provider_installation rules from cubtera config
+use serde_json::Value;
+use std::path::Path;
+
+use super::remote_state::hcl_expr;
+
+pub(super) const CLI_CONFIG_FILE: &str = "cubtera_cli.tfrc";
+
+// Base is the user CLI config (~/.terraformrc), settings defined there are not generated again
+pub(super) fn cli_config(
+    base: Option<&str>,
+    plugin_cache_dir: &Path,
+    plugins_mirror: Option<&Path>,
+    rules: &[Value],
+) -> String {
+    let base = base.unwrap_or_default();
+    let mut sections = Vec::new();
+    if !base.trim().is_empty() {
+        sections.push(format!("{}\n", base.trim_end()));
+    }
+
+    if !has_setting(base, "plugin_cache_dir") {
+        sections.push(format!(
+            "plugin_cache_dir = {}\n",
+            hcl_expr(&Value::String(plugin_cache_dir.to_string_lossy().into()))
+        ));
+    }
+
+    if !has_setting(base, "provider_installation") {
+        let mut methods = Vec::new();
+        if let Some(mirror) = plugins_mirror {
+            methods.push(format!(
+                "  filesystem_mirror {{\n    path = {}\n  }}\n",
+                hcl_expr(&Value::String(mirror.to_string_lossy().into()))
+            ));
+        }
+        methods.extend(rules.iter().filter_map(method_block));
+        // providers missing in the mirror are downloaded from their origin registries
+        if rules.is_empty() {
+            methods.push("  direct {}\n".into());
+        }
+        sections.push(format!(
+            "provider_installation {{\n{}}}\n",
+            methods.join("")
+        ));
+    }
+
+    sections.join("\n")
+}
+
+// Uncommented attribute or block with the name, e.g. `plugin_cache_dir = ...` or `provider_installation {`
+fn has_setting(config: &str, name: &str) -> bool {
+    config.lines().map(str::trim_start).any(|line| {
+        line.strip_prefix(name)
+            .and_then(|rest| rest.trim_start().chars().next())
+            .is_some_and(|c| c == '=' || c == '{')
+    })
+}
+
+// { "method": "network_mirror", "url": "...", "include": [...] } => network_mirror { ... } block
+fn method_block(rule: &Value) -> Option<String> {
+    let rule = rule.as_object()?;
+    let method = rule.get("method")?.as_str()?;
+    let attributes = rule
+        .iter()
+        .filter(|(key, _)| *key != "method")
+        .map(|(key, value)| format!("    {} = {}\n", attribute_name(key), hcl_expr(value)))
+        .collect::<String>();
+    Some(format!("  {method} {{\n{attributes}  }}\n"))
+}
+
+// dev_overrides attributes are provider addresses, e.g. "hashicorp/aws"
+fn attribute_name(key: &str) -> String {
+    if key
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        key.into()
+    } else {
+        Value::String(key.into()).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_cli_config() {
+        let config = cli_config(
+            None,
+            Path::new("/home/ci/.cubtera/plugin-cache"),
+            Some(Path::new("/work/plugins")),
+            &[],
+        );
+        assert_eq!(
+            config,
+            r#"plugin_cache_dir = "/home/ci/.cubtera/plugin-cache"
+
+provider_installation {
+  filesystem_mirror {
+    path = "/work/plugins"
+  }
+  direct {}
+}
+"#
+        );
+
+        let config = cli_config(
+            Some("# plugin_cache_dir = \"/old\"\n// provider_installation { direct {} }\n"),
+            Path::new("/cache"),
+            None,
+            &[],
+        );
+        assert!(config.contains("\nplugin_cache_dir = \"/cache\"\n"));
+        assert!(config.contains("\nprovider_installation {\n  direct {}\n}\n"));
+
+        let rules = [
+            json!({ "method": "network_mirror", "url": "https://mirror.local/", "include": ["registry.terraform.io/*/*"] }),
+            json!({ "method": "dev_overrides", "hashicorp/null": "/dev/null-provider" }),
+            json!({ "url": "no method" }),
+        ];
+        let config = cli_config(
+            Some("plugin_cache_dir = \"/cache\"\n"),
+            Path::new("/unused"),
+            None,
+            &rules,
+        );
+        assert_eq!(
+            config,
+            r#"plugin_cache_dir = "/cache"
+
+provider_installation {
+  network_mirror {
+    url = "https://mirror.local/"
+    include = ["registry.terraform.io/*/*"]
+  }
+  dev_overrides {
+    "hashicorp/null" = "/dev/null-provider"
+  }
+}
+"#
+        );
+    }
+}
@@ -4,25 +4,42 @@ use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::io::Write;
 use std::net::TcpListener;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use yansi::Paint;
 
-mod tfswitch;
+mod cli_config;
+mod outputs;
+mod plan;
+mod remote_state;
+pub(super) mod tfswitch;
 
+use super::params::RunnerParams;
 use super::{Runner, RunnerLoad};
 use crate::prelude::*;
+use crate::utils::lock::FileLock;
+use crate::utils::process::run_process;
 use tfswitch::tf_switch;
 
+// Resolves runner binary path by runner params when runner_command is not defined
+pub(super) type BinSwitch = fn(&RunnerParams) -> Result<PathBuf, Box<dyn std::error::Error>>;
+
+// Lock held while init is running, released on drop
+#[allow(dead_code)]
+enum InitLock {
+    File(FileLock),
+    Port(TcpListener),
+}
+
 pub struct TfRunner {
     load: RunnerLoad,
     ctx: Value,
+    bin_switch: BinSwitch,
 }
 
 impl Runner for TfRunner {
     fn new(load: RunnerLoad) -> Self {
-        let ctx = Value::Object(serde_json::Map::new());
-        TfRunner { load, ctx }
+        TfRunner::with_bin_switch(load, tf_switch)
     }
 
     fn get_load(&self) -> &RunnerLoad {
@@ -42,19 +59,20 @@ impl Runner for TfRunner {
 
         match self.load.command.first() {
             Some(command) if command == "init" => {
-                self.load.unit.remove_temp_folder();
-                self.load.unit.copy_files();
+                self.load.unit.remove_temp_folder()?;
+                self.load.unit.copy_files()?;
                 self.create_state_backend()?;
             }
             _ => {
                 if !self.load.unit.temp_folder.exists() {
-                    exit_with_error(format!(
+                    return Err(CubteraError::Runner(format!(
                         "Can't find unit temp folder {:?}. Run init command first.",
                         &self.load.unit.temp_folder
-                    ));
+                    ))
+                    .into());
                 }
                 if GLOBAL_CFG.always_copy_files {
-                    self.load.unit.copy_files();
+                    self.load.unit.copy_files()?;
                     self.create_state_backend()?;
                 }
             }
@@ -67,11 +85,11 @@ impl Runner for TfRunner {
         debug!(target: "tf runner", "Convert dims data files into terraform format");
 
         // read all files started with dim_ and json extension
-        let files = std::fs::read_dir(&self.load.unit.temp_folder)
-            .unwrap_or_exit(format!(
-                "Can't read unit temp folder: {:?}",
-                self.load.unit.temp_folder
-            ))
+        let mut files = std::fs::read_dir(&self.load.unit.temp_folder)
+            .or_error(
+                CubteraError::Runner,
+                format!("Can't read unit temp folder: {:?}", self.load.unit.temp_folder),
+            )?
             .filter_map(|entry| entry.ok())
             .map(|entry| entry.path())
             .filter(|entry| entry.is_file())
@@ -94,13 +112,18 @@ impl Runner for TfRunner {
                     .contains(".auto.tfvars")
             })
             .collect::<Vec<PathBuf>>();
+        // read_dir order is not defined, sorted files keep cubtera_vars.tf stable
+        files.sort();
 
         // for each file read json as value and create list of root keys
         #[allow(clippy::format_collect)]
         if !files.is_empty() {
             let dim_tf_variables: String = files
                 .iter()
-                .filter_map(read_json_file)
+                .map(read_json_file)
+                .collect::<CubteraResult<Vec<Option<Value>>>>()?
+                .into_iter()
+                .flatten()
                 .filter_map(|json: serde_json::Value| json.as_object().map(|obj| obj.to_owned()))
                 .flat_map(|obj| obj.into_iter().map(|(k, _)| k))
                 .map(|key| {
@@ -122,34 +145,60 @@ impl Runner for TfRunner {
         }
 
         // rename all files to dim_<dim_name>.auto.tfvars.json
-        files.iter().for_each(|file| {
+        for file in &files {
             let new_file = file.with_file_name(format!(
                 "{}.auto.tfvars.json",
                 file.file_stem().unwrap_or_default().to_string_lossy() //.trim_start_matches("dim_")
             ));
             std::fs::rename(file, new_file)
-                .unwrap_or_exit(format!("Can't rename file: {:?}", file));
-        });
+                .or_error(CubteraError::Runner, format!("Can't rename file: {:?}", file))?;
+        }
 
         Ok(())
     }
 
+    // Same files as init, regardless of the command and always_copy_files
+    fn render(&mut self) -> Result<Value, Box<dyn std::error::Error>> {
+        self.load.unit.remove_temp_folder()?;
+        self.load.unit.copy_files()?;
+        self.create_state_backend()?;
+        self.change_files()?;
+        self.update_ctx("render", json!("executed"));
+
+        Ok(self.ctx.clone())
+    }
+
     fn runner(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         let mut tf_args: Vec<String> = Vec::new();
         let mut run_command = self.load.command.clone();
+        let saved_plan = self.get_saved_plan();
 
         if let Some(command) = self.load.command.first() {
             match command.as_str() {
                 "plan" | "apply" | "destroy" | "refresh" | "console" | "import" => {
                     // check if unit temp folder is not empty
                     if !self.load.unit.temp_folder.exists() {
-                        exit_with_error(format!(
+                        return Err(CubteraError::Runner(format!(
                             "Can't find unit temp folder {:?}. Run init command first.",
                             &self.load.unit.temp_folder
-                        ));
+                        ))
+                        .into());
                     }
                     // extend vars with required env vars from unit manifest
-                    tf_args.extend(self.tf_vars_args());
+                    // saved plan already contains all vars and can't be applied with them
+                    if !(command == "apply" && saved_plan.is_some()) {
+                        tf_args.extend(self.tf_vars_args()?);
+                    }
+                    if command == "plan" {
+                        if let Some(plan_file) = &self.load.params.plan_file {
+                            if !run_command.iter().any(|arg| arg.starts_with("-out")) {
+                                tf_args.push(format!("-out={plan_file}"));
+                            }
+                        }
+                        if self.load.params.is_detailed_exitcode() && !self.has_detailed_exitcode_arg() {
+                            tf_args.push("-detailed-exitcode".into());
+                        }
+                    }
 
                     if command == "import" {
                         let import_params: Vec<String> = run_command.iter().skip(1).map(|s| s.to_string()).collect();
@@ -174,22 +223,40 @@ impl Runner for TfRunner {
             }
         }
 
-        let tf_path = match params.runner_command {
-            Some(bin_path) => {
+        let (tf_path, tf_global_args) = match params.get_runner_program()? {
+            Some(program) => {
                 info!(target: "tf runner", "Use custom binary path...");
-                string_to_path(&bin_path)
+                program
             }
             None => {
-                info!(target: "tf runner", "Run terraform version {}", &params.version.yellow());
-                tf_switch(&params.version).unwrap_or_exit(format!(
-                    "Failed to switch to terraform version {}",
-                    params.version
-                ))
+                info!(target: "tf runner", "Run version {}", &params.version.yellow());
+                let tf_path = (self.bin_switch)(&params).or_error(
+                    CubteraError::Runner,
+                    format!("Failed to switch to version {}", params.version),
+                )?;
+                (tf_path, vec![])
             }
         };
 
-        if let Some(extra_params) = &self.load.params.extra_args {
-            tf_args.extend(extra_params.split(" ").map(|s| s.to_string()));
+        tf_args.extend(self.load.params.get_extra_args()?);
+
+        // saved plan must be the last apply argument
+        if let Some(plan_path) = &saved_plan {
+            let hash = plan::plan_hash(plan_path)?;
+            if let Ok(approved_hash) = std::env::var(plan::PLAN_HASH_ENV) {
+                if approved_hash != hash {
+                    return Err(CubteraError::Runner(format!(
+                        "Saved plan hash {} doesn't match approved {} hash {}",
+                        hash, plan::PLAN_HASH_ENV, approved_hash
+                    ))
+                    .into());
+                }
+            }
+            info!(target: "tf runner", "Apply saved plan {} with hash {}",
+                plan_path.to_string_lossy().blue(), hash.blue());
+            self.update_ctx("plan_file", json!(plan_path.to_string_lossy()));
+            self.update_ctx("plan_hash", json!(hash));
+            tf_args.push(plan_path.to_string_lossy().to_string());
         }
 
         info!(target: "tf runner", "Command: {} {}",
@@ -200,101 +267,241 @@ impl Runner for TfRunner {
         // start terraform with all required arguments
         let mut tf_command = Command::new(&tf_path);
 
-        let mut socket: Option<TcpListener> = None;
         // check if another instance is running with init and wait for it to finish
-        if matches!(&self.load.command.as_slice(), [cmd, ..] if cmd == "init") {
-            let delay = rand::rng().random_range(800..1200);
-
-            loop {
-                match TcpListener::bind(("0.0.0.0", self.load.params.get_lock_port())) {
-                    Ok(listener) => {
-                        socket = Some(listener);
-                        break;
-                    }
-                    Err(_) => {
-                        info!(target: "tf runner", "Waiting for unlock while init in parallel");
-                        std::thread::sleep(std::time::Duration::from_millis(delay));
-                    }
-                }
-            }
-        };
+        let init_lock = matches!(&self.load.command.as_slice(), [cmd, ..] if cmd == "init")
+            .then(|| self.lock_init())
+            .transpose()?;
 
         debug!(target: "tf runner", "Extra args: {}", &tf_args.join(" ").blue());
 
-        let mut child = tf_command
+        if let Some(cli_config) = self.write_cli_config()? {
+            debug!(target: "tf runner", "CLI config: {}", cli_config.to_string_lossy().blue());
+        }
+
+        tf_command
             .current_dir(self.load.unit.temp_folder.to_str().unwrap())
+            .args(&tf_global_args)
             .args(run_command)
             .args(tf_args)
             .envs(self.get_env_tf_vars())
             //.env("TF_DATA_DIR", &self.config.temp_folder_path)
-            //.env("TF_PLUGIN_CACHE_DIR", "~/.terraform.d/plugin-cache")
             //.env("TF_DATA_DIR", &self.config.temp_folder_path)
             //.env("TF_CLI_ARGS", "-compact-warnings")
-            // TODO: implement different exit status configuration only for apply and plan commands:
-            //.env("TF_CLI_ARGS","-detailed-exitcode")
             .env("TF_IN_AUTOMATION", "true")
-            .env("TF_INPUT", "0")
-            .spawn()
-            .unwrap_or_exit(format!(
-                "Failed to start {:?} with args {:?}",
-                tf_path, &self.load.command
-            ));
+            .env("TF_INPUT", "0");
+
+        let result = run_process(&mut tf_command, &self.process_options("runner"))
+            .or_error(
+                CubteraError::Runner,
+                format!("Failed to run {:?} with args {:?}", tf_path, &self.load.command),
+            )?;
+        self.check_termination("runner", &result);
+
+        let mut exit_code = result.code();
+
+        let is_plan = matches!(self.load.command.as_slice(), [cmd, ..] if cmd == "plan");
+        // with detailed exit codes plan returns 0 - no changes, 1 - error, 2 - changes present
+        let detailed_exitcode =
+            self.load.params.is_detailed_exitcode() || self.has_detailed_exitcode_arg();
+        let plan_status = (is_plan && detailed_exitcode).then_some(match exit_code {
+            0 => "no_changes",
+            2 => "changes",
+            _ => "error",
+        });
 
-        let result = child
-            .wait()
-            .unwrap_or_exit("Failed to get terraform exitcode".to_string());
+        if let Some(plan_status) = plan_status {
+            info!(target: "tf runner", "Plan status: {}", plan_status.blue());
+            self.update_ctx("plan_status", json!(plan_status));
+            self.update_ctx("tf_exit_code", json!(exit_code));
+            if plan_status == "changes" {
+                exit_code = self.load.params.get_drift_exit_code();
+            }
+        }
 
-        let exit_code = result.code().unwrap_or(1);
+        if is_plan && plan_status.map_or(exit_code == 0, |status| status != "error") {
+            self.save_plan_artifacts(&tf_path)?;
+        }
 
         let tf_command = GLOBAL_CFG.dlog_db.clone().and(
-            matches!(self.load.command.as_slice(), [cmd, ..] if cmd == "apply")
-                .then_some("apply")
-                .or(
-                    matches!(self.load.command.as_slice(), [cmd, ..] if cmd == "destroy")
-                        .then_some("destroy"),
-                ),
+            match self.load.command.first().map(|cmd| cmd.as_str()) {
+                Some("apply") => Some("apply"),
+                Some("destroy") => Some("destroy"),
+                Some("plan") if plan_status.is_some() => Some("plan"),
+                _ => None,
+            },
         );
 
         if let Some(tf_command) = tf_command {
-            let dlog = Dlog::build(self.load.unit.clone(), tf_command.into(), exit_code);
+            let mut dlog = self.build_dlog(tf_command, result.code());
+            if let Some(plan_status) = plan_status {
+                dlog = dlog.with_drift_status(plan_status);
+            }
             let _ = dlog
                 .put(&GLOBAL_CFG.org)
                 .check_with_warn("Can't put dlog to DB");
             info!(target: "tf runner", "Dlog data was saved");
         }
 
-        if socket.is_some() {
+        if init_lock.is_some() {
             debug!(target: "tf runner", "Unlocking parallel run after finishing init command");
-            drop(socket);
+            drop(init_lock);
+        }
+
+        self.update_ctx("exit_code", json!(exit_code));
+
+        let applied = matches!(self.load.command.first(), Some(cmd) if cmd == "apply") && exit_code == 0;
+        // infrastructure is already changed, failed outputs saving doesn't fail the run
+        if applied && self.load.params.is_save_outputs() {
+            if let Err(e) = self
+                .save_outputs(&tf_path, &tf_global_args)
+                .check_with_warn("Can't save outputs to inventory")
+            {
+                self.update_ctx("outputs", json!({ "error": e.to_string() }));
+            }
+        }
+
+        if let Some(plan_path) = saved_plan.as_ref().filter(|_| applied) {
+            debug!(target: "tf runner", "Remove applied plan {}", plan_path.to_string_lossy().blue());
+            let _ = plan::remove_plan(plan_path).check_with_warn("Can't remove applied plan");
         }
 
         if !GLOBAL_CFG.clean_cache {
             debug!(target: "tf runner", "Ignore cache cleaning due to global config");
-            self.update_ctx("exit_code", json!(exit_code));
-
             return Ok(());
         }
 
-        if exit_code == 0 {
-            if let Some(first_command) = self.load.command.first() {
-                if first_command == "apply"
-                    || self
-                        .load
-                        .command
-                        .contains(&"--detailed-exitcode".to_owned())
-                {
-                    debug!(target: "tf runner", "Remove temp folder after successful {} command", "apply".blue());
-                    self.load.unit.remove_temp_folder();
-                }
-            }
+        // nothing to apply after successful apply or plan without changes
+        if applied || plan_status == Some("no_changes") {
+            debug!(target: "tf runner", "Remove temp folder after successful {} command", self.load.command[0].blue());
+            let _ = self
+                .load
+                .unit
+                .remove_temp_folder()
+                .check_with_warn("Can't clean cache");
         }
 
-        self.update_ctx("exit_code", json!(exit_code));
         Ok(())
     }
 }
 
 impl TfRunner {
+    pub(super) fn with_bin_switch(load: RunnerLoad, bin_switch: BinSwitch) -> Self {
+        let ctx = Value::Object(serde_json::Map::new());
+        TfRunner {
+            load,
+            ctx,
+            bin_switch,
+        }
+    }
+
+    // Detailed exit codes could be passed with plan command as well as enabled with runner param
+    fn has_detailed_exitcode_arg(&self) -> bool {
+        self.load
+            .command
+            .iter()
+            .any(|arg| arg == "-detailed-exitcode" || arg == "--detailed-exitcode")
+    }
+
+    // Saved plan file in unit temp folder to apply, if plan_file param is set
+    // and plan file is not passed explicitly with apply command
+    fn get_saved_plan(&self) -> Option<PathBuf> {
+        let plan_file = self.load.params.plan_file.as_ref()?;
+        let (command, args) = self.load.command.split_first()?;
+        if command != "apply" || plan::has_plan_arg(args, &self.load.unit.temp_folder) {
+            return None;
+        }
+
+        let plan_path = self.load.unit.temp_folder.join(plan_file);
+        if !plan_path.exists() {
+            warn!(target: "tf runner", "Saved plan {} is not found, apply without saved plan", plan_file.blue());
+            return None;
+        }
+        Some(plan_path)
+    }
+
+    // Renders saved plan to json and adds plan file, hash and changes summary to ctx
+    fn save_plan_artifacts(&mut self, tf_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(plan_file) = &self.load.params.plan_file else {
+            return Ok(());
+        };
+        let plan_path = self.load.unit.temp_folder.join(plan_file);
+        if !plan_path.exists() {
+            return Ok(());
+        }
+
+        let hash = plan::plan_hash(&plan_path)?;
+        let (json_path, plan_json) = plan::render_plan_json(tf_path, &plan_path, self.get_env_tf_vars())?;
+        let summary = plan::plan_summary(&plan_json);
+
+        info!(target: "tf runner", "Plan saved to {} with hash {}", plan_path.to_string_lossy().blue(), hash.blue());
+
+        self.update_ctx("plan_file", json!(plan_path.to_string_lossy()));
+        self.update_ctx("plan_json", json!(json_path.to_string_lossy()));
+        self.update_ctx("plan_hash", json!(hash));
+        self.update_ctx("plan_summary", summary);
+
+        Ok(())
+    }
+
+    // Saves outputs after apply to the inventory of the unit primary dimension
+    fn save_outputs(
+        &mut self,
+        tf_path: &std::path::Path,
+        tf_global_args: &[String],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let outputs_json = outputs::read_outputs(
+            tf_path,
+            tf_global_args,
+            &self.load.unit.temp_folder,
+            self.get_env_tf_vars(),
+        )?;
+        let (values, sensitive) = outputs::outputs_values(&outputs_json);
+        if !sensitive.is_empty() {
+            warn!(target: "tf runner", "Sensitive outputs are not saved to inventory: {}", sensitive.join(", ").yellow());
+        }
+
+        let dim = self.load.unit.save_outputs(values.clone())?;
+        info!(target: "tf runner", "Outputs were saved to dimension {}", dim.blue());
+        self.update_ctx("outputs", json!({ "dim": dim, "values": values }));
+
+        Ok(())
+    }
+
+    // Serializes parallel init runs (shared plugins cache) with a file lock in ~/.cubtera/locks.
+    // Legacy TCP port lock (lock_port param) is used if lock file can't be created
+    fn lock_init(&self) -> CubteraResult<InitLock> {
+        let params = &self.load.params;
+        let lock_name = format!("init-{}", params.get_lock_port());
+
+        match FileLock::acquire(&lock_name, params.get_lock_timeout()) {
+            Ok(lock) => return Ok(InitLock::File(lock)),
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                return Err(CubteraError::Runner(format!("Can't start init: {e}")))
+            }
+            Err(e) => {
+                warn!(target: "tf runner", "Can't use lock file ({e}), fallback to port {} lock", params.get_lock_port())
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let delay = rand::rng().random_range(800..1200);
+        loop {
+            match TcpListener::bind(("0.0.0.0", params.get_lock_port())) {
+                Ok(listener) => return Ok(InitLock::Port(listener)),
+                Err(_) if start.elapsed() < params.get_lock_timeout() => {
+                    info!(target: "tf runner", "Waiting for unlock while init in parallel");
+                    std::thread::sleep(std::time::Duration::from_millis(delay));
+                }
+                Err(_) => {
+                    return Err(CubteraError::Runner(format!(
+                        "Can't start init: timeout waiting for port {} lock",
+                        params.get_lock_port()
+                    )))
+                }
+            }
+        }
+    }
+
     fn get_env_tf_vars(&self) -> HashMap<String, String> {
         // get all env vars started with TF_VAR_
         let mut env_vars: HashMap<String, String> = std::env::vars()
@@ -307,6 +514,11 @@ impl TfRunner {
         env_vars.insert("TF_VAR_unit_name".into(), self.load.unit.name.clone());
         env_vars.insert("TF_VAR_dim_tree".into(), self.load.unit.get_unit_state_path());
 
+        let cli_config = self.load.unit.temp_folder.join(cli_config::CLI_CONFIG_FILE);
+        if cli_config.exists() && std::env::var_os("TF_CLI_CONFIG_FILE").is_none() {
+            env_vars.insert("TF_CLI_CONFIG_FILE".into(), cli_config.to_string_lossy().into());
+        }
+
         let kids: Vec<_> = self.load.unit.dimensions
             .iter()
             .filter_map(|dim| dim.kids.clone())
@@ -339,6 +551,51 @@ impl TfRunner {
         env_vars
     }
 
+    // Generated CLI config in unit temp folder, TF_CLI_CONFIG_FILE set by user is used as is.
+    // Replaces copying of plugins folder to ~/.terraform.d/plugins, racy in parallel runs
+    fn write_cli_config(&self) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        let params = &self.load.params;
+        let temp_folder = &self.load.unit.temp_folder;
+        if !temp_folder.exists() {
+            return Ok(None);
+        }
+
+        // is plugins_path absolute => current_dir will be overwritten by join method
+        let plugins_folder_path = std::env::current_dir()?.join(&GLOBAL_CFG.plugins_path);
+        let plugins_mirror = plugins_folder_path.exists().then_some(plugins_folder_path.as_path());
+        if plugins_mirror.is_none() {
+            debug!(target: "tf runner", "Plugin folder {:?} does not exist, filesystem mirror is not used", plugins_folder_path);
+        }
+
+        if !params.is_cli_config() || std::env::var_os("TF_CLI_CONFIG_FILE").is_some() {
+            if let Some(plugins) = plugins_mirror {
+                copy_plugins(plugins)?;
+            }
+            return Ok(None);
+        }
+
+        let plugin_cache_dir = string_to_path(&params.plugin_cache_dir);
+        std::fs::create_dir_all(&plugin_cache_dir).or_error(
+            CubteraError::Runner,
+            format!("Can't create plugin cache folder: {:?}", plugin_cache_dir),
+        )?;
+
+        let base = std::env::var("HOME")
+            .ok()
+            .and_then(|home| std::fs::read_to_string(Path::new(&home).join(".terraformrc")).ok());
+        let config = cli_config::cli_config(
+            base.as_deref(),
+            &plugin_cache_dir,
+            plugins_mirror,
+            params.provider_installation.as_deref().unwrap_or_default(),
+        );
+
+        let path = temp_folder.join(cli_config::CLI_CONFIG_FILE);
+        std::fs::write(&path, config)
+            .or_error(CubteraError::Runner, format!("Can't write CLI config: {:?}", path))?;
+        Ok(Some(path))
+    }
+
     fn create_state_backend(&self) -> Result<(), Box<dyn std::error::Error>> {
         // create tf backend config hcl file
         let tf_hcl = json!({
@@ -350,10 +607,17 @@ impl TfRunner {
         let path = self.load.unit.temp_folder.join("cubtera_backend.tf");
         convert_json_to_hcl_file(&tf_hcl, path)?;
 
+        // remote state data blocks of units from manifest depends_on list
+        let remote_state = remote_state::remote_state_to_hcl(&self.load.dependencies);
+        if !remote_state.is_empty() {
+            let path = self.load.unit.temp_folder.join(remote_state::REMOTE_STATE_FILE);
+            std::fs::write(path, remote_state)?;
+        }
+
         Ok(())
     }
 
-    fn tf_vars_args(&self) -> Vec<String> {
+    fn tf_vars_args(&self) -> CubteraResult<Vec<String>> {
         let mut tf_vars_args: Vec<String> = Vec::new();
         // extend vars with required env vars from unit manifest
         if let Some(spec) = &self.load.unit.manifest.spec {
@@ -365,7 +629,8 @@ impl TfRunner {
                             format!(
                                 "{}={}",
                                 var.0,
-                                std::env::var(var.1).unwrap_or_exit(format!("Required {}", var.1))
+                                std::env::var(var.1)
+                                    .or_error(CubteraError::Runner, format!("Required {}", var.1))?
                             ),
                         ]);
                     }
@@ -381,7 +646,7 @@ impl TfRunner {
             }
         }
 
-        tf_vars_args
+        Ok(tf_vars_args)
     }
 }
 
@@ -452,3 +717,13 @@ fn convert_json_to_hcl_file(json: &Value, output_file: PathBuf) -> std::io::Resu
     std::fs::write(output_file, hcl_content.as_bytes())?;
     Ok(())
 }
+
+// Without generated CLI config plugins are copied to ~/.terraform.d/plugins,
+// the implicit local mirror terraform uses when CLI config has no provider_installation block
+fn copy_plugins(plugins_folder_path: &Path) -> CubteraResult<()> {
+    let home = std::env::var("HOME").or_error(CubteraError::Runner, "Can't copy plugins folder".into())?;
+    let plugins_dir = Path::new(&home).join(".terraform.d/plugins");
+    debug!(target: "tf runner", "CLI config is not generated, copy plugins folder to {:?}", plugins_dir);
+    copy_folder(plugins_folder_path.to_path_buf(), &plugins_dir, false)
+        .or_error(CubteraError::Runner, "Failed to copy plugins folder".into())
+}
@@ -0,0 +1,73 @@
+// Terraform outputs after apply: `output -json` reading and conversion to inventory values
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+pub(super) fn read_outputs(
+    tf_path: &Path,
+    tf_global_args: &[String],
+    working_dir: &Path,
+    env_vars: HashMap<String, String>,
+) -> Result<Value, Box<dyn std::error::Error>> {
+    let output = Command::new(tf_path)
+        .current_dir(working_dir)
+        .args(tf_global_args)
+        .args(["output", "-json"])
+        .envs(env_vars)
+        .env("TF_IN_AUTOMATION", "true")
+        .stdout(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Failed to read outputs with output -json: {}",
+            output.status
+        )
+        .into());
+    }
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+// Output values by name, sensitive outputs are not saved to the inventory
+pub(super) fn outputs_values(outputs_json: &Value) -> (Value, Vec<String>) {
+    let mut sensitive = Vec::new();
+    let values = outputs_json
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, output)| {
+            if output["sensitive"].as_bool().unwrap_or(false) {
+                sensitive.push(name.clone());
+                return None;
+            }
+            Some((name.clone(), output["value"].clone()))
+        })
+        .collect::<Map<String, Value>>();
+
+    (Value::Object(values), sensitive)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_outputs_values() {
+        let outputs_json = json!({
+            "vpc_id": { "sensitive": false, "type": "string", "value": "vpc-123" },
+            "subnets": { "sensitive": false, "type": ["list", "string"], "value": ["a", "b"] },
+            "db_password": { "sensitive": true, "type": "string", "value": "secret" },
+        });
+
+        let (values, sensitive) = outputs_values(&outputs_json);
+        assert_eq!(
+            values,
+            json!({ "vpc_id": "vpc-123", "subnets": ["a", "b"] })
+        );
+        assert_eq!(sensitive, ["db_password"]);
+        assert_eq!(outputs_values(&json!({})).0, json!({}));
+    }
+}
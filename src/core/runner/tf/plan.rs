@@ -0,0 +1,146 @@
+// Saved plan artifacts: plan file hash, `show -json` rendering and changes summary
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+// Env var with the approved plan hash, apply fails if saved plan hash is different
+pub(super) const PLAN_HASH_ENV: &str = "CUBTERA_PLAN_HASH";
+
+pub(super) fn plan_hash(plan_path: &Path) -> std::io::Result<String> {
+    use sha2::Digest;
+
+    let plan = std::fs::read(plan_path)?;
+    Ok(format!("{:x}", sha2::Sha256::digest(plan)))
+}
+
+// Apply gets an explicit plan as the last argument, options values (e.g. -target module.x) are not plans
+pub(super) fn has_plan_arg(apply_args: &[String], dir: &Path) -> bool {
+    apply_args
+        .last()
+        .is_some_and(|arg| !arg.starts_with('-') && dir.join(arg).is_file())
+}
+
+// Removes applied plan and its json rendering, so it can't be applied again
+pub(super) fn remove_plan(plan_path: &Path) -> std::io::Result<()> {
+    let mut json_path = plan_path.as_os_str().to_owned();
+    json_path.push(".json");
+    let _ = std::fs::remove_file(json_path);
+    std::fs::remove_file(plan_path)
+}
+
+// Renders saved plan with `show -json` to <plan_file>.json next to the plan file
+pub(super) fn render_plan_json(
+    tf_path: &Path,
+    plan_path: &Path,
+    env_vars: HashMap<String, String>,
+) -> Result<(PathBuf, Value), Box<dyn std::error::Error>> {
+    let output = Command::new(tf_path)
+        .current_dir(plan_path.parent().unwrap_or(Path::new(".")))
+        .args(["show", "-json"])
+        .arg(plan_path)
+        .envs(env_vars)
+        .env("TF_IN_AUTOMATION", "true")
+        .stdout(Stdio::piped())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!("Failed to render plan {:?} with show -json", plan_path).into());
+    }
+
+    let plan_json: Value = serde_json::from_slice(&output.stdout)?;
+    let mut json_path = plan_path.as_os_str().to_owned();
+    json_path.push(".json");
+    let json_path = PathBuf::from(json_path);
+    std::fs::write(&json_path, &output.stdout)?;
+
+    Ok((json_path, plan_json))
+}
+
+// Counts resource changes the same way terraform does: replace is add + destroy
+pub(super) fn plan_summary(plan_json: &Value) -> Value {
+    let (mut add, mut change, mut destroy) = (0, 0, 0);
+
+    plan_json
+        .get("resource_changes")
+        .and_then(|changes| changes.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|resource| resource.pointer("/change/actions"))
+        .filter_map(|actions| actions.as_array())
+        .for_each(|actions| {
+            actions
+                .iter()
+                .filter_map(|a| a.as_str())
+                .for_each(|action| match action {
+                    "create" => add += 1,
+                    "update" => change += 1,
+                    "delete" => destroy += 1,
+                    _ => {}
+                })
+        });
+
+    json!({ "add": add, "change": change, "destroy": destroy })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_summary() {
+        let plan_json = json!({
+            "resource_changes": [
+                { "change": { "actions": ["create"] } },
+                { "change": { "actions": ["update"] } },
+                { "change": { "actions": ["delete", "create"] } },
+                { "change": { "actions": ["no-op"] } },
+                { "change": { "actions": ["read"] } },
+                { "change": { "actions": ["delete"] } },
+            ]
+        });
+
+        assert_eq!(
+            plan_summary(&plan_json),
+            json!({ "add": 2, "change": 1, "destroy": 2 })
+        );
+        assert_eq!(
+            plan_summary(&json!({})),
+            json!({ "add": 0, "change": 0, "destroy": 0 })
+        );
+    }
+
+    #[test]
+    fn test_plan_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let plan_path = dir.path().join("cubtera.tfplan");
+        std::fs::write(&plan_path, "plan").unwrap();
+
+        assert_eq!(
+            plan_hash(&plan_path).unwrap(),
+            "64879f7d6b960a01909762d911a32d4582c20010c5641ee90278b644a9e3b525"
+        );
+    }
+
+    #[test]
+    fn test_has_plan_arg() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("reviewed.tfplan"), "plan").unwrap();
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<String>>();
+
+        assert!(has_plan_arg(&args(&["reviewed.tfplan"]), dir.path()));
+        assert!(has_plan_arg(
+            &args(&["-lock=false", "reviewed.tfplan"]),
+            dir.path()
+        ));
+        assert!(!has_plan_arg(&args(&["-target", "module.x"]), dir.path()));
+        assert!(!has_plan_arg(&args(&["-auto-approve"]), dir.path()));
+        assert!(!has_plan_arg(&[], dir.path()));
+
+        let plan_path = dir.path().join("reviewed.tfplan");
+        std::fs::write(dir.path().join("reviewed.tfplan.json"), "{}").unwrap();
+        remove_plan(&plan_path).unwrap();
+        assert!(!plan_path.exists());
+        assert!(!dir.path().join("reviewed.tfplan.json").exists());
+    }
+}
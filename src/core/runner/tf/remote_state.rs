@@ -0,0 +1,81 @@
+// terraform_remote_state data blocks for units from manifest depends_on list
+use serde_json::Value;
+
+pub(super) const REMOTE_STATE_FILE: &str = "cubtera_remote_state.tf";
+
+// Renders data blocks from { "<unit_name>": { "<state_type>": { <config> } } }
+pub(super) fn remote_state_to_hcl(dependencies: &Value) -> String {
+    dependencies
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(unit_name, backend)| {
+            let (backend_type, config) = backend.as_object()?.iter().next()?;
+            let config = config
+                .as_object()
+                .into_iter()
+                .flatten()
+                .map(|(key, value)| format!("    {} = {}\n", key, hcl_expr(value)))
+                .collect::<String>();
+            Some(format!(
+                "data \"terraform_remote_state\" \"{}\" {{\n  backend = \"{}\"\n  config = {{\n{}  }}\n}}\n",
+                unit_name, backend_type, config
+            ))
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// HCL expression of a json value, objects are rendered as object expressions (not blocks)
+pub(super) fn hcl_expr(value: &Value) -> String {
+    match value {
+        Value::Object(map) => format!(
+            "{{ {} }}",
+            map.iter()
+                .map(|(key, value)| format!("{} = {}", key, hcl_expr(value)))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+        Value::Array(arr) => format!(
+            "[{}]",
+            arr.iter().map(hcl_expr).collect::<Vec<String>>().join(", ")
+        ),
+        // json string escapes are valid in HCL strings, "${" has to be escaped
+        Value::String(_) => value.to_string().replace("${", "$${").replace("%{", "%%{"),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_remote_state_to_hcl() {
+        let dependencies = json!({
+            "network": {
+                "s3": {
+                    "bucket": "tf-states",
+                    "key": "cubtera/dc:stg1/network.tfstate",
+                    "assume_role": { "role_arn": "arn:aws:iam::1:role/tf" },
+                }
+            }
+        });
+
+        assert_eq!(
+            remote_state_to_hcl(&dependencies),
+            r#"data "terraform_remote_state" "network" {
+  backend = "s3"
+  config = {
+    bucket = "tf-states"
+    key = "cubtera/dc:stg1/network.tfstate"
+    assume_role = { role_arn = "arn:aws:iam::1:role/tf" }
+  }
+}
+"#
+        );
+        assert_eq!(remote_state_to_hcl(&json!({})), "");
+        assert_eq!(hcl_expr(&json!("a${b}")), r#""a$${b}""#);
+    }
+}
@@ -1,48 +1,119 @@
-use std::net::TcpListener;
-use std::ops::{Add, Not, Rem};
+use std::ops::Not;
+use crate::core::error::*;
 use crate::utils::helper::*;
+use crate::core::runner::params::RunnerParams;
+use crate::utils::lock::FileLock;
 
-use rand::Rng;
 use std::path::{Path, PathBuf};
 use log::{debug, info};
 
-pub fn tf_switch(tf_version: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
-    let version = match tf_version {
-        "latest" => get_latest(),
-        _ => tf_version.into(),
-    };
+const TF_DOWNLOAD_URL: &str = "https://releases.hashicorp.com/terraform";
+const TF_VERSIONS_URL: &str = "https://api.releases.hashicorp.com/v1/releases/terraform/latest";
+const TF_CACHE_PATH: &str = "~/.cubtera/tf";
+const TOFU_DOWNLOAD_URL: &str = "https://github.com/opentofu/opentofu/releases/download";
+const TOFU_VERSIONS_URL: &str = "https://get.opentofu.org/tofu/api.json";
+const TOFU_CACHE_PATH: &str = "~/.cubtera/tofu";
+const DOWNLOAD_LOCK_TIMEOUT: u64 = 120;
+
+// Release archive of a runner binary with its checksums and signature locations
+struct Release {
+    bin_name: &'static str,
+    version: String,
+    cache_path: &'static str,
+    archive_url: String,
+    sums_url: String,
+    signature_url: String,
+    public_key: Option<String>,
+    archives_path: Option<PathBuf>,
+    offline: bool,
+}
 
-    let _ = semver::Version::parse(&version).unwrap_or_exit(format!(
-        "Failed to parse tf version {version}. Use semver format.",
-    ));
+pub fn tf_switch(params: &RunnerParams) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let version = match params.version.as_str() {
+        "latest" if params.is_offline() => get_cached_latest("terraform", TF_CACHE_PATH)?,
+        "latest" => get_latest(params.versions_url.as_deref().unwrap_or(TF_VERSIONS_URL))?,
+        _ => params.version.clone(),
+    };
 
-    let path = format!("~/.cubtera/tf/{version}").replace('~', &std::env::var("HOME").unwrap());
+    let os = get_os();
+    let base_url = params
+        .download_url
+        .as_deref()
+        .unwrap_or(TF_DOWNLOAD_URL)
+        .trim_end_matches('/');
+    let base_url = format!("{base_url}/{version}");
+
+    info!(target: "tf switch", "Use terraform version {version}");
+    bin_switch(&Release {
+        bin_name: "terraform",
+        archive_url: format!("{base_url}/terraform_{version}_{os}.zip"),
+        sums_url: format!("{base_url}/terraform_{version}_SHA256SUMS"),
+        signature_url: format!("{base_url}/terraform_{version}_SHA256SUMS.sig"),
+        public_key: params.public_key.clone(),
+        archives_path: params.archives_path.as_deref().map(string_to_path),
+        offline: params.is_offline(),
+        cache_path: TF_CACHE_PATH,
+        version,
+    })
+}
 
-    let tf_folder = Path::new(&path).to_path_buf();
-    let tf_path = tf_folder.join("terraform");
+pub fn tofu_switch(params: &RunnerParams) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let version = match params.version.as_str() {
+        "latest" if params.is_offline() => get_cached_latest("tofu", TOFU_CACHE_PATH)?,
+        "latest" => get_tofu_latest(params.versions_url.as_deref().unwrap_or(TOFU_VERSIONS_URL))?,
+        _ => params.version.trim_start_matches('v').into(),
+    };
 
-    // take random delay to avoid parallel downloads
-    let delay = rand::rng().random_range(100..800);
-    std::thread::sleep(std::time::Duration::from_millis(delay));
+    let os = get_os();
+    let base_url = params
+        .download_url
+        .as_deref()
+        .unwrap_or(TOFU_DOWNLOAD_URL)
+        .trim_end_matches('/');
+    let base_url = format!("{base_url}/v{version}");
+
+    info!(target: "tf switch", "Use OpenTofu version {version}");
+    bin_switch(&Release {
+        bin_name: "tofu",
+        archive_url: format!("{base_url}/tofu_{version}_{os}.zip"),
+        sums_url: format!("{base_url}/tofu_{version}_SHA256SUMS"),
+        signature_url: format!("{base_url}/tofu_{version}_SHA256SUMS.gpgsig"),
+        public_key: params.public_key.clone(),
+        archives_path: params.archives_path.as_deref().map(string_to_path),
+        offline: params.is_offline(),
+        cache_path: TOFU_CACHE_PATH,
+        version,
+    })
+}
 
-    loop {
-        match is_file_available(&tf_path) {
-            true => return Ok(tf_path),
-            false => rock_n_roll(&version, &tf_folder)?
-        }
+// Returns path to the cached binary <cache_path>/<version>/<bin_name>,
+// downloads and extracts the release archive if binary is not cached yet
+fn bin_switch(release: &Release) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let _ = semver::Version::parse(&release.version).or_error(
+        CubteraError::Runner,
+        format!(
+            "Failed to parse {} version {}. Use semver format.",
+            release.bin_name, release.version
+        ),
+    )?;
+
+    let bin_folder = string_to_path(release.cache_path).join(&release.version);
+    let bin_path = bin_folder.join(release.bin_name);
+
+    if is_file_available(&bin_path) {
+        return Ok(bin_path);
     }
-}
 
-fn rock_n_roll(version: &str, tf_folder: &PathBuf ) -> Result<(), Box<dyn std::error::Error>> {
-    let port = version.replace('.', "").parse::<u16>()
-        .unwrap_or_default()
-        .rem(5430).add(60000);
+    let lock_name = format!("{}-{}", release.bin_name, release.version);
+    let _lock = FileLock::acquire(&lock_name, std::time::Duration::from_secs(DOWNLOAD_LOCK_TIMEOUT))
+        .map_err(|e| format!("Can't lock {} download: {e}", release.bin_name))?;
 
-    match acquire_lock(port) {
-        Ok(_lock) => download_tf(&tf_folder, &version)?,
-        Err(_) => wait_for_lock(port)?
+    // binary could be downloaded by a parallel run while waiting for the lock
+    if !is_file_available(&bin_path) {
+        download_release(release, &bin_folder)?;
     }
-    Ok(())
+
+    Ok(bin_path)
 }
 
 fn is_file_available(path: &PathBuf) -> bool {
@@ -61,85 +132,227 @@ fn is_file_available(path: &PathBuf) -> bool {
         .is_ok()
 }
 
-fn acquire_lock(port: u16) -> std::io::Result<TcpListener> {
-    TcpListener::bind(("0.0.0.0", port))
-}
-
-fn wait_for_lock(port: u16) -> Result<(), Box<dyn std::error::Error>> {
-    let start = std::time::Instant::now();
-    info!(target: "tf switch", "Waiting for terraform download in parallel, port {port} locked");
-    while start.elapsed() < std::time::Duration::from_secs(120) {
-        match TcpListener::bind(("0.0.0.0", port)) {
-            Ok(_) => return Ok(()),
-            Err(_) => std::thread::sleep(std::time::Duration::from_millis(1000)),
-        }
+fn download_release(release: &Release, bin_folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let bin_name = release.bin_name;
+    if !bin_folder.exists() {
+        std::fs::create_dir_all(bin_folder)?;
+    }
+    let zip_path = bin_folder.join("tmp.zip");
+
+    let body = fetch_artifact(release, &release.archive_url).map_err(|e| {
+        format!("Error getting {bin_name} binary version {}: {e}", release.version)
+    })?;
+
+    std::fs::write(&zip_path, &body)
+        .or_error(CubteraError::Runner, format!("Unable to save {bin_name} zip file"))?;
+
+    // don't extract anything from the archive until its integrity is verified
+    if let Err(e) = verify_release(release, bin_folder, &body) {
+        std::fs::remove_file(&zip_path)?;
+        return Err(Box::from(format!(
+            "Integrity check of {bin_name} version {} failed: {e}",
+            release.version
+        )));
     }
-    Err(Box::from(format!("Timeout waiting for terraform download lock on port {port}")))
+
+    debug!(target: "", "Unzipping {bin_name} from {}", zip_path.display());
+
+    // Open the downloaded zip file
+    let zip_file = std::fs::File::open(&zip_path)
+        .or_error(CubteraError::Runner, format!("Failed to open {bin_name} zip file"))?;
+
+    let mut archive = zip::read::ZipArchive::new(zip_file)
+        .or_error(CubteraError::Runner, format!("Failed to read {bin_name} zip file"))?;
+
+    archive.extract(bin_folder)?;
+
+    std::fs::remove_file(&zip_path)
+        .or_error(CubteraError::Runner, format!("Failed to remove {bin_name} zip file"))?;
+
+    Ok(())
 }
 
-fn download_tf(tf_folder: &PathBuf, version: &str) -> Result<(), Box<dyn std::error::Error>> {
-    if !tf_folder.exists() {
-        std::fs::create_dir_all(tf_folder.clone())?;
+// Verifies archive hash with the release SHA256SUMS file
+// and SHA256SUMS signature if public key is configured
+fn verify_release(release: &Release, bin_folder: &Path, archive: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let sums = fetch_artifact(release, &release.sums_url)?;
+
+    if let Some(public_key) = &release.public_key {
+        let signature = fetch_artifact(release, &release.signature_url)?;
+        verify_signature(&sums, &signature, &string_to_path(public_key), bin_folder)?;
+        info!(target: "tf switch", "Checksums signature of {} is verified", release.bin_name);
     }
-    std::fs::File::create(tf_folder.join("tmp.zip"))?;
 
-    let os = get_os();
-    let url = format!(
-        "https://releases.hashicorp.com/terraform/{version}/terraform_{version}_{os}.zip",
-    );
+    let file_name = release
+        .archive_url
+        .rsplit('/')
+        .next()
+        .unwrap_or_default();
+    verify_checksum(archive, &String::from_utf8_lossy(&sums), file_name)?;
+    debug!(target: "", "Checksum of {file_name} is verified");
 
-    debug!(target: "", "Downloading TF zip archive: {}", url);
+    Ok(())
+}
 
-    let response =
-        reqwest::blocking::get(url).unwrap_or_exit("Error downloading TF zip file".into());
+// Reads release artifact from pre-seeded archives folder if it exists there, downloads it otherwise
+fn fetch_artifact(release: &Release, url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let file_name = url.rsplit('/').next().unwrap_or_default();
 
-    if !response.status().is_success() {
+    let local_file = release
+        .archives_path
+        .as_ref()
+        .map(|path| path.join(file_name))
+        .filter(|path| path.is_file());
 
-        std::fs::remove_file(tf_folder.join("tmp.zip"))?;
+    if let Some(local_file) = local_file {
+        debug!(target: "", "Use pre-seeded file: {}", local_file.display());
+        return Ok(std::fs::read(local_file)?);
+    }
 
-        exit_with_error(format!(
-            "Error downloading TF binary version {}. Status: {}",
-            version,
-            response.status()
-        ));
+    if release.offline {
+        return Err(Box::from(format!(
+            "{file_name} is not found in archives path and can't be downloaded in offline mode"
+        )));
     }
 
-    let body = response.bytes().unwrap_or_else(|_| {
-        exit_with_error("Error downloading TF zip file".to_string());
-    });
+    debug!(target: "", "Downloading: {url}");
+    fetch(url)
+}
 
-    std::fs::write(tf_folder.join("tmp.zip"), body).unwrap_or_else(|_| {
-        exit_with_error("Unable to save TF zip file".to_string());
-    });
+fn fetch(url: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let response = reqwest::blocking::get(url)?;
+    if !response.status().is_success() {
+        return Err(Box::from(format!("Can't download {url}. Status: {}", response.status())));
+    }
+    Ok(response.bytes()?.to_vec())
+}
 
-    debug!(target: "", "Unzipping TF from {}", tf_folder.join("tmp.zip").display());
+fn verify_checksum(archive: &[u8], sums: &str, file_name: &str) -> Result<(), String> {
+    use sha2::Digest;
 
-    // Open the downloaded zip file
-    let zip_file = std::fs::File::open(tf_folder.join("tmp.zip"))
-        .unwrap_or_exit("Failed to open TF zip file".to_string());
+    let expected = sums
+        .lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(_, name)| name.trim().trim_start_matches('*') == file_name)
+        .map(|(hash, _)| hash.to_lowercase())
+        .ok_or(format!("Checksum for {file_name} is not found in SHA256SUMS"))?;
 
-    let mut archive = zip::read::ZipArchive::new(zip_file)
-        .unwrap_or_exit("Failed to read TF zip file".to_string());
+    let actual = format!("{:x}", sha2::Sha256::digest(archive));
 
-    archive.extract(tf_folder.clone())?;
+    match actual == expected {
+        true => Ok(()),
+        false => Err(format!("Checksum mismatch for {file_name}: expected {expected}, got {actual}")),
+    }
+}
 
-    std::fs::remove_file(tf_folder.join("tmp.zip"))
-        .unwrap_or_exit("Failed to remove TF zip file".to_string());
+// Verifies detached signature with gpg in a temporary keyring, so user's keyring is not affected
+fn verify_signature(sums: &[u8], signature: &[u8], public_key: &Path, bin_folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let gpg_home = bin_folder.join("tmp.gnupg");
+    std::fs::create_dir_all(&gpg_home)?;
+    std::fs::set_permissions(&gpg_home, std::fs::Permissions::from_mode(0o700))?;
+    std::fs::write(gpg_home.join("SHA256SUMS"), sums)?;
+    std::fs::write(gpg_home.join("SHA256SUMS.sig"), signature)?;
+
+    let gpg = |args: &[&std::ffi::OsStr]| {
+        std::process::Command::new("gpg")
+            .arg("--batch")
+            .arg("--homedir")
+            .arg(&gpg_home)
+            .args(args)
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+    };
 
-    Ok(())
+    let result = gpg(&["--import".as_ref(), public_key.as_os_str()])
+        .map_err(|e| format!("Can't run gpg: {e}"))
+        .and_then(|status| match status.success() {
+            true => Ok(()),
+            false => Err(format!("Can't import public key {public_key:?}")),
+        })
+        .and_then(|_| {
+            gpg(&[
+                "--verify".as_ref(),
+                gpg_home.join("SHA256SUMS.sig").as_os_str(),
+                gpg_home.join("SHA256SUMS").as_os_str(),
+            ])
+            .map_err(|e| format!("Can't run gpg: {e}"))
+        })
+        .and_then(|status| match status.success() {
+            true => Ok(()),
+            false => Err("SHA256SUMS signature is not valid".to_string()),
+        });
+
+    let _ = std::fs::remove_dir_all(&gpg_home);
+    result.map_err(Box::from)
 }
 
-fn get_latest() -> String {
+fn get_latest(versions_url: &str) -> CubteraResult<String> {
     let resp =
-        reqwest::blocking::get("https://api.releases.hashicorp.com/v1/releases/terraform/latest")
-            .unwrap_or_exit("Can't define TF latest version".into())
+        reqwest::blocking::get(versions_url)
+            .or_error(CubteraError::Runner, "Can't define TF latest version".into())?
             .json::<serde_json::Value>()
-            .unwrap_or_exit("Can't parse TF version response".into());
+            .or_error(CubteraError::Runner, "Can't parse TF version response".into())?;
 
     resp["version"]
         .as_str()
-        .unwrap_or_exit("Can't parse TF version response".into())
-        .to_string()
+        .map(ToString::to_string)
+        .or_error(CubteraError::Runner, "Can't parse TF version response".into())
+}
+
+// OpenTofu versions api returns all released versions including pre-releases,
+// latest is the highest stable one
+fn get_tofu_latest(versions_url: &str) -> CubteraResult<String> {
+    let resp = reqwest::blocking::get(versions_url)
+        .or_error(CubteraError::Runner, "Can't define OpenTofu latest version".into())?
+        .json::<serde_json::Value>()
+        .or_error(CubteraError::Runner, "Can't parse OpenTofu versions response".into())?;
+
+    latest_stable_version(&resp).or_error(
+        CubteraError::Runner,
+        "Can't find OpenTofu stable version in versions response".into(),
+    )
+}
+
+// Offline mode: latest is the highest stable version already cached in <cache_path>/<version>/<bin_name>
+fn get_cached_latest(bin_name: &str, cache_path: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let cache_folder = string_to_path(cache_path);
+    let versions = std::fs::read_dir(&cache_folder)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.join(bin_name).is_file())
+                .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+
+    latest_cached_version(&versions).ok_or(Box::from(format!(
+        "Offline mode: no cached {bin_name} versions found in {cache_folder:?}"
+    )))
+}
+
+fn latest_cached_version(versions: &[String]) -> Option<String> {
+    versions
+        .iter()
+        .filter_map(|version| semver::Version::parse(version).ok())
+        .filter(|version| version.pre.is_empty())
+        .max()
+        .map(|version| version.to_string())
+}
+
+fn latest_stable_version(versions: &serde_json::Value) -> Option<String> {
+    versions["versions"]
+        .as_array()?
+        .iter()
+        .filter_map(|v| v["id"].as_str())
+        .filter_map(|id| semver::Version::parse(id.trim_start_matches('v')).ok())
+        .filter(|version| version.pre.is_empty())
+        .max()
+        .map(|version| version.to_string())
 }
 
 fn get_os() -> String {
@@ -170,3 +383,49 @@ fn get_os_architecture<'a>() -> &'a str {
         _ => panic!("Unsupported OS architecture: {}", arch),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_latest_stable_version() {
+        let versions = json!({
+            "versions": [
+                { "id": "1.9.0-alpha1" },
+                { "id": "1.8.2" },
+                { "id": "1.10.0-rc1" },
+                { "id": "1.8.10" },
+                { "id": "broken" },
+            ]
+        });
+        assert_eq!(latest_stable_version(&versions), Some("1.8.10".to_string()));
+        assert_eq!(latest_stable_version(&json!({})), None);
+    }
+
+    #[test]
+    fn test_latest_cached_version() {
+        let versions = ["1.5.7", "1.10.1", "1.9.8", "tmp", "1.11.0-beta1"]
+            .map(String::from)
+            .to_vec();
+        assert_eq!(latest_cached_version(&versions), Some("1.10.1".to_string()));
+        assert_eq!(latest_cached_version(&[]), None);
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        use sha2::Digest;
+
+        let archive = b"terraform archive";
+        let hash = format!("{:x}", sha2::Sha256::digest(archive));
+        let sums = format!(
+            "{}  terraform_1.6.6_darwin_arm64.zip\n{hash}  terraform_1.6.6_linux_amd64.zip\n",
+            "0".repeat(64)
+        );
+
+        assert!(verify_checksum(archive, &sums, "terraform_1.6.6_linux_amd64.zip").is_ok());
+        assert!(verify_checksum(archive, &sums, "terraform_1.6.6_darwin_arm64.zip").is_err());
+        assert!(verify_checksum(archive, &sums, "terraform_1.6.6_linux_arm64.zip").is_err());
+    }
+}
@@ -1,8 +1,7 @@
 use serde_json::Value;
 use std::error::Error;
 
-use crate::prelude::*;
-
+use super::tf::tfswitch::tofu_switch;
 use super::tf::TfRunner;
 use super::{Runner, RunnerLoad};
 
@@ -14,13 +13,9 @@ impl TofuRunner {}
 
 impl Runner for TofuRunner {
     fn new(load: RunnerLoad) -> Self {
-        // let ctx = Value::Object(serde_json::Map::new());
-
-        match load.params.runner_command {
-            Some(_) => TofuRunner {
-                inner: TfRunner::new(load),
-            },
-            _ => exit_with_error("OpenTofu runner command is not defined".to_string()),
+        // runner_command has priority, otherwise OpenTofu binary is resolved by runner.version
+        TofuRunner {
+            inner: TfRunner::with_bin_switch(load, tofu_switch),
         }
     }
 
@@ -44,6 +39,10 @@ impl Runner for TofuRunner {
         self.inner.change_files()
     }
 
+    fn render(&mut self) -> Result<Value, Box<dyn Error>> {
+        self.inner.render()
+    }
+
     fn run(&mut self) -> Result<Value, Box<dyn std::error::Error>> {
         self.inner.run()
     }
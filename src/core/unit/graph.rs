@@ -0,0 +1,314 @@
+// Dependency graph of units from manifest depends_on lists, used for multi-unit runs
+use crate::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// What to do with the rest of the graph when a unit fails.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FailurePolicy {
+    /// Don't start new units, running ones are finished.
+    Stop,
+    /// Run all units which don't depend on the failed one.
+    Continue,
+}
+
+impl FailurePolicy {
+    pub fn str_to_policy(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "continue" => FailurePolicy::Continue,
+            _ => FailurePolicy::Stop,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UnitRunStatus {
+    Success,
+    Changes, // plan with changes, not a failure
+    Failed(i32),
+    Skipped(String),
+}
+
+impl UnitRunStatus {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, UnitRunStatus::Success | UnitRunStatus::Changes)
+    }
+}
+
+impl std::fmt::Display for UnitRunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UnitRunStatus::Success => write!(f, "success"),
+            UnitRunStatus::Changes => write!(f, "changes"),
+            UnitRunStatus::Failed(code) => write!(f, "failed ({code})"),
+            UnitRunStatus::Skipped(reason) => write!(f, "skipped ({reason})"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UnitRun {
+    pub name: String,
+    pub status: UnitRunStatus,
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct UnitGraph {
+    units: Vec<String>,
+    deps: HashMap<String, Vec<String>>, // unit -> units it depends on, only units of the graph
+}
+
+impl UnitGraph {
+    /// Builds the graph from units with their `depends_on` lists.
+    /// Dependencies which are not in the list are ignored (expected to be applied already).
+    pub fn new(units: &[(String, Vec<String>)]) -> CubteraResult<Self> {
+        let names = units
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<String>>();
+        let deps = units
+            .iter()
+            .map(|(name, deps)| {
+                let deps = deps
+                    .iter()
+                    .filter(|dep| {
+                        let known = names.contains(dep);
+                        if !known {
+                            debug!(target: "graph", "Dependency {dep} of unit {name} is not in the run list. Ignored");
+                        }
+                        known
+                    })
+                    .cloned()
+                    .collect::<Vec<String>>();
+                (name.clone(), deps)
+            })
+            .collect();
+
+        let graph = UnitGraph { units: names, deps };
+        graph.check_cycles()?;
+        Ok(graph)
+    }
+
+    /// Units in topological order, dependencies first. Reverse order (dependents first) is used for destroy.
+    /// Units without relations keep the input order.
+    pub fn order(&self, reverse: bool) -> Vec<String> {
+        let mut order = Vec::new();
+        while order.len() < self.units.len() {
+            let next = self
+                .units
+                .iter()
+                .find(|unit| {
+                    !order.contains(*unit)
+                        && self
+                            .prerequisites(unit, reverse)
+                            .iter()
+                            .all(|p| order.contains(p))
+                })
+                .expect("graph is checked for cycles")
+                .clone();
+            order.push(next);
+        }
+        order
+    }
+
+    /// Runs units in topological order with up to `parallelism` units at once.
+    /// A unit is started when all its prerequisites are finished successfully,
+    /// units after a failed one are skipped. Results are returned in the graph order.
+    pub fn execute<F>(
+        &self,
+        reverse: bool,
+        parallelism: usize,
+        policy: FailurePolicy,
+        run_unit: F,
+    ) -> Vec<UnitRun>
+    where
+        F: Fn(&str) -> UnitRunStatus + Sync,
+    {
+        let order = self.order(reverse);
+        let mut results: HashMap<String, UnitRun> = HashMap::new();
+        let mut started: HashSet<String> = HashSet::new();
+        let mut running = 0;
+        let mut stopped = false;
+
+        std::thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel::<UnitRun>();
+            loop {
+                // skip units after failed or skipped prerequisites
+                for unit in &order {
+                    if started.contains(unit) || results.contains_key(unit) {
+                        continue;
+                    }
+                    if let Some(failed) = self
+                        .prerequisites(unit, reverse)
+                        .into_iter()
+                        .find(|p| results.get(p).is_some_and(|r| !r.status.is_ok()))
+                    {
+                        results.insert(
+                            unit.clone(),
+                            skipped(unit, &format!("{failed} is not succeeded")),
+                        );
+                    }
+                }
+
+                if !stopped {
+                    for unit in &order {
+                        if running >= parallelism.max(1) {
+                            break;
+                        }
+                        let ready = self
+                            .prerequisites(unit, reverse)
+                            .iter()
+                            .all(|p| results.get(p).is_some_and(|r| r.status.is_ok()));
+                        if started.contains(unit) || results.contains_key(unit) || !ready {
+                            continue;
+                        }
+                        started.insert(unit.clone());
+                        running += 1;
+
+                        let tx = tx.clone();
+                        let run_unit = &run_unit;
+                        let unit = unit.clone();
+                        scope.spawn(move || {
+                            let start = Instant::now();
+                            let status = run_unit(&unit);
+                            let _ = tx.send(UnitRun {
+                                name: unit,
+                                status,
+                                duration: start.elapsed(),
+                            });
+                        });
+                    }
+                }
+
+                if running == 0 {
+                    break;
+                }
+                let Ok(run) = rx.recv() else { break };
+                running -= 1;
+                if !run.status.is_ok() && policy == FailurePolicy::Stop {
+                    stopped = true;
+                }
+                results.insert(run.name.clone(), run);
+            }
+        });
+
+        order
+            .into_iter()
+            .map(|unit| {
+                results
+                    .remove(&unit)
+                    .unwrap_or_else(|| skipped(&unit, "not started"))
+            })
+            .collect()
+    }
+
+    // Units which have to be finished before the unit: its dependencies, or its dependents in reverse order
+    fn prerequisites(&self, unit: &str, reverse: bool) -> Vec<String> {
+        if reverse {
+            self.units
+                .iter()
+                .filter(|other| self.deps[*other].iter().any(|dep| dep == unit))
+                .cloned()
+                .collect()
+        } else {
+            self.deps[unit].clone()
+        }
+    }
+
+    fn check_cycles(&self) -> CubteraResult<()> {
+        // Kahn's algorithm: units left after removing all units without dependencies are in a cycle
+        let mut left = self.units.clone();
+        while let Some(pos) = left
+            .iter()
+            .position(|unit| self.deps[unit].iter().all(|dep| !left.contains(dep)))
+        {
+            left.remove(pos);
+        }
+        if !left.is_empty() {
+            return Err(CubteraError::Unit(format!(
+                "Units dependency cycle detected between units: {left:?}. Check depends_on in unit manifests"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn skipped(unit: &str, reason: &str) -> UnitRun {
+    UnitRun {
+        name: unit.into(),
+        status: UnitRunStatus::Skipped(reason.into()),
+        duration: Duration::ZERO,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn graph() -> UnitGraph {
+        UnitGraph::new(&[
+            ("dns".into(), vec!["network".into()]),
+            (
+                "app".into(),
+                vec!["dns".into(), "network".into(), "external".into()],
+            ),
+            ("network".into(), vec![]),
+            ("monitoring".into(), vec![]),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_order() {
+        let graph = graph();
+        assert_eq!(graph.order(false), ["network", "dns", "app", "monitoring"]);
+        assert_eq!(graph.order(true), ["app", "dns", "network", "monitoring"]);
+
+        let cycle = UnitGraph::new(&[
+            ("a".into(), vec!["b".into()]),
+            ("b".into(), vec!["a".into()]),
+            ("c".into(), vec![]),
+        ]);
+        assert!(matches!(cycle, Err(CubteraError::Unit(e)) if e.contains(r#"["a", "b"]"#)));
+    }
+
+    #[test]
+    fn test_execute() {
+        let started = Mutex::new(Vec::new());
+        let results = graph().execute(false, 2, FailurePolicy::Continue, |unit| {
+            started.lock().unwrap().push(unit.to_string());
+            match unit {
+                "dns" => UnitRunStatus::Failed(1),
+                _ => UnitRunStatus::Success,
+            }
+        });
+        let statuses = results
+            .iter()
+            .map(|r| (r.name.as_str(), r.status.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                ("network", UnitRunStatus::Success),
+                ("dns", UnitRunStatus::Failed(1)),
+                ("app", UnitRunStatus::Skipped("dns is not succeeded".into())),
+                ("monitoring", UnitRunStatus::Success),
+            ]
+        );
+        assert!(!started.lock().unwrap().contains(&"app".to_string()));
+
+        // stop policy doesn't start new units after the failure
+        let results = graph().execute(false, 1, FailurePolicy::Stop, |unit| match unit {
+            "network" => UnitRunStatus::Failed(1),
+            _ => UnitRunStatus::Success,
+        });
+        assert_eq!(results[0].status, UnitRunStatus::Failed(1));
+        assert_eq!(
+            results[3].status,
+            UnitRunStatus::Skipped("not started".into())
+        );
+    }
+}
@@ -21,8 +21,10 @@ pub struct Manifest {
     pub unit_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub spec: Option<Spec>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "depends_on")]
+    pub depends_on: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub runner: Option<HashMap<String, String>>,
+    pub runner: Option<HashMap<String, serde_json::Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub state: Option<HashMap<String, String>>,
 }
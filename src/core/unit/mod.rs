@@ -1,3 +1,4 @@
+pub mod graph;
 mod manifest;
 use manifest::Manifest;
 
@@ -13,6 +14,14 @@ use crate::prelude::data::Storage;
 use crate::prelude::*;
 use crate::utils::helper::*;
 
+// Unit from depends_on list of the unit manifest
+#[derive(Debug, Clone)]
+pub struct UnitDependency {
+    pub name: String,
+    pub manifest: Manifest,
+    pub state_path: String, // dim tree of the dependency for the unit dimensions
+}
+
 #[derive(Debug, Clone)]
 pub struct Unit {
     pub name: String,
@@ -23,6 +32,8 @@ pub struct Unit {
     opt_dims: Option<Vec<Dim>>,
     unit_folder: PathBuf,
     generic_unit_folder: Option<PathBuf>,
+    storage: Storage,
+    context: Option<String>,
 }
 
 impl Unit {
@@ -32,39 +43,19 @@ impl Unit {
         extensions: &[String],
         storage: &Storage,
         context: Option<String>,
-    ) -> Self {
-        // Check if unit exists
-        let mut unit_folder = Path::new(&GLOBAL_CFG.units_path).join(&name);
-        let org_unit_folder = Path::new(&GLOBAL_CFG.units_path)
-            .join(&GLOBAL_CFG.org)
-            .join(&name);
-
-        let (manifest, generic_unit_folder) = match Manifest::load(&org_unit_folder) {
-            Ok(manifest) => {
-                let generic_unit_folder = Manifest::load(&unit_folder)
-                    .ok()
-                    .map(|_| unit_folder.clone());
-
-                unit_folder.clone_from(&org_unit_folder);
-                (manifest, generic_unit_folder)
-            }
-            Err(e) => match Manifest::load(&unit_folder) {
-                Ok(manifest) => {
-                    debug!(target: "", "Unit {name} can't be load from org folder: {org_unit_folder:?} with error: {e}. Using generic unit from {unit_folder:?}");
-                    (manifest, None)
-                }
-                Err(e) => exit_with_error(format!(
-                    "Can't proceed with unit {}. {e}, ", name.red(),
-                )),
-            },
-        };
+    ) -> CubteraResult<Self> {
+        let (manifest, unit_folder, generic_unit_folder) = Unit::load_manifest(&name)?;
 
         // Check if all required dimensions were provided
-        manifest.dimensions.iter().for_each(|dim| {
-            if !dimensions.iter().any(|x| x.starts_with(dim)) {
-                exit_with_error(format!("Required dimension [{dim}] was not provided.",))
-            }
-        });
+        if let Some(dim) = manifest
+            .dimensions
+            .iter()
+            .find(|dim| !dimensions.iter().any(|x| x.starts_with(*dim)))
+        {
+            return Err(CubteraError::Unit(format!(
+                "Required dimension [{dim}] was not provided.",
+            )));
+        }
 
         // Sort dimensions by order in manifest.dimensions list
         let mut sorted_dimensions = dimensions.to_vec();
@@ -95,12 +86,16 @@ impl Unit {
         }
 
         // define all optional dimensions from manifest.optDims for empty values generation
-        let opt_dims: Option<Vec<Dim>> = manifest.opt_dims.clone().map(|opt_dims| {
-            opt_dims
-                .iter()
-                .map(|dim_type| DimBuilder::new_undefined(dim_type).build())
-                .collect::<Vec<Dim>>()
-        });
+        let opt_dims: Option<Vec<Dim>> = manifest
+            .opt_dims
+            .clone()
+            .map(|opt_dims| {
+                opt_dims
+                    .iter()
+                    .map(|dim_type| DimBuilder::new_undefined(dim_type).build())
+                    .collect::<CubteraResult<Vec<Dim>>>()
+            })
+            .transpose()?;
         let temp_folder = Path::new(&GLOBAL_CFG.temp_folder_path)
             .join(&GLOBAL_CFG.org)
             .join(&name)
@@ -108,9 +103,9 @@ impl Unit {
             .join(extensions.join("/"));
 
         let extensions = extensions.to_vec();
-        let dimensions = Unit::get_dims_from_cli(&provided_required_dims, storage, context);
+        let dimensions = Unit::get_dims_from_cli(&provided_required_dims, storage, context.clone())?;
 
-        Unit {
+        Ok(Unit {
             name,
             manifest,
             temp_folder,
@@ -119,11 +114,76 @@ impl Unit {
             dimensions,
             extensions,
             opt_dims,
+            storage: storage.clone(),
+            context,
+        })
+    }
+
+    // Unit manifest with unit folder from org units folder, or generic unit folder if org one is missing
+    fn load_manifest(name: &str) -> CubteraResult<(Manifest, PathBuf, Option<PathBuf>)> {
+        let unit_folder = Path::new(&GLOBAL_CFG.units_path).join(name);
+        let org_unit_folder = Path::new(&GLOBAL_CFG.units_path)
+            .join(&GLOBAL_CFG.org)
+            .join(name);
+
+        match Manifest::load(&org_unit_folder) {
+            Ok(manifest) => {
+                let generic_unit_folder = Manifest::load(&unit_folder)
+                    .ok()
+                    .map(|_| unit_folder.clone());
+                Ok((manifest, org_unit_folder, generic_unit_folder))
+            }
+            Err(e) => match Manifest::load(&unit_folder) {
+                Ok(manifest) => {
+                    debug!(target: "", "Unit {name} can't be load from org folder: {org_unit_folder:?} with error: {e}. Using generic unit from {unit_folder:?}");
+                    Ok((manifest, unit_folder, None))
+                }
+                Err(e) => Err(CubteraError::Unit(format!(
+                    "Can't proceed with unit {}. {e}, ",
+                    name.red(),
+                ))),
+            },
         }
     }
 
-    #[must_use]
-    pub fn build(self) -> Self {
+    /// Names of org and generic units with all required dimensions in the provided list, sorted.
+    pub fn list_names(dimensions: &[String]) -> CubteraResult<Vec<String>> {
+        let units_path = Path::new(&GLOBAL_CFG.units_path);
+        let mut names = [units_path.join(&GLOBAL_CFG.org), units_path.to_path_buf()]
+            .iter()
+            .filter(|path| path.is_dir())
+            .map(|path| {
+                std::fs::read_dir(path)
+                    .or_error(CubteraError::Unit, format!("Can't read units folder: {path:?}"))
+            })
+            .collect::<CubteraResult<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().join("manifest.toml").is_file())
+            .filter_map(|entry| entry.file_name().to_str().map(String::from))
+            .collect::<Vec<String>>();
+        names.sort();
+        names.dedup();
+
+        Ok(names
+            .into_iter()
+            .filter(|name| match Unit::load_manifest(name) {
+                Ok((manifest, _, _)) => manifest
+                    .dimensions
+                    .iter()
+                    .all(|dim| dimensions.iter().any(|x| x.starts_with(dim))),
+                Err(e) => {
+                    warn!(target: "", "{e}");
+                    false
+                }
+            })
+            .collect())
+    }
+
+    /// Checks unit allow/deny lists and affinity tags against provided dimensions.
+    /// Returns `CubteraError::Skipped` if the unit must not run with these dimensions.
+    pub fn build(self) -> CubteraResult<Self> {
         // list all unit's dimensions with their parents
         let dims_set: HashSet<_> = self
             .dimensions
@@ -135,8 +195,7 @@ impl Unit {
             let allow_set: HashSet<_> = allow_list.clone().into_iter().collect();
             let allow_intersect: Vec<_> = allow_set.intersection(&dims_set).collect();
             if allow_intersect.is_empty() {
-                warn!(target: "", "Any of provided dims {dims_set:?} was not ALLOWED for this unit. Check unit manifest 'allowList': {allow_list:?}. Execution terminated...");
-                std::process::exit(0);
+                return Err(CubteraError::Skipped(format!("Any of provided dims {dims_set:?} was not ALLOWED for this unit. Check unit manifest 'allowList': {allow_list:?}. Execution terminated...")));
             }
         }
         // check if even one provided dims is denied (included parent dim)
@@ -144,8 +203,7 @@ impl Unit {
             let deny_set: HashSet<_> = deny_list.clone().into_iter().collect();
             let deny_set_intersect: Vec<_> = deny_set.intersection(&dims_set).collect();
             if !deny_set_intersect.is_empty() {
-                warn!(target: "", "Some of provided dims {dims_set:?} was DENIED for this unit. Check unit manifest 'denyList': {deny_list:?}. Execution terminated...");
-                std::process::exit(0);
+                return Err(CubteraError::Skipped(format!("Some of provided dims {dims_set:?} was DENIED for this unit. Check unit manifest 'denyList': {deny_list:?}. Execution terminated...")));
             }
         }
 
@@ -161,35 +219,32 @@ impl Unit {
                 .collect::<Vec<&str>>();
             if let Some(unit_tags) = &self.manifest.affinity_tags {
                 if value_intersection(affinity_tags.clone(), json!(unit_tags)).is_none() {
-                    warn!(target: "", "Unit {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", self.name);
-                    std::process::exit(0);
+                    return Err(CubteraError::Skipped(format!("Unit {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", self.name)));
                 }
-                self.dimensions.iter().for_each(|dim| {
+                for dim in &self.dimensions {
                     let dim_data = dim.get_dim_data();
                     let dim_tags = dim_data["meta"].get("affinity_tags").cloned().unwrap_or_default();
                     if value_intersection(affinity_tags.clone(), dim_tags.clone()).is_none(){
-                        warn!(target: "", "Dimension {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", dim_data["name"].as_str().unwrap_or_default());
-                        std::process::exit(0);
+                        return Err(CubteraError::Skipped(format!("Dimension {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", dim_data["name"].as_str().unwrap_or_default())));
                     }
-                });
+                }
             } else {
-                warn!(target: "", "Unit {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", self.name.blue());
-                std::process::exit(0);
+                return Err(CubteraError::Skipped(format!("Unit {:?} doesn't have required affinity tags. Allowed tags: {allowed_tags:?}. Execution terminated...", self.name.blue())));
             }
         }
 
-        self
+        Ok(self)
     }
 
     fn get_dims_from_cli(
         dim_names: &[String],
         storage: &Storage,
         context: Option<String>,
-    ) -> Vec<Dim> {
+    ) -> CubteraResult<Vec<Dim>> {
         dim_names
             .iter()
             .map(|dim| DimBuilder::new_from_cli(dim, &GLOBAL_CFG.org, storage, context.clone()))
-            .collect::<Vec<Dim>>()
+            .collect::<CubteraResult<Vec<Dim>>>()
     }
 
     pub fn get_unit_state_path(&self) -> String {
@@ -208,21 +263,97 @@ impl Unit {
         dims.join("/")
     }
 
-    pub fn remove_temp_folder(&self) {
+    // Units from manifest depends_on list with their state path for the current unit dimensions.
+    // Dependency state path uses its required dimensions (unit dims or their parents)
+    // and provided optional ones, without extensions.
+    pub fn get_dependencies(&self) -> CubteraResult<Vec<UnitDependency>> {
+        let all_dims = self.get_all_dims();
+        self.manifest
+            .depends_on
+            .iter()
+            .flatten()
+            .map(|name| {
+                let (manifest, _, _) = Unit::load_manifest(name)?;
+                let required = manifest
+                    .dimensions
+                    .iter()
+                    .map(|dim_type| {
+                        all_dims
+                            .iter()
+                            .copied()
+                            .find(|dim| &dim.dim_type == dim_type)
+                            .or_error(
+                                CubteraError::Unit,
+                                format!("Dependency unit {name} requires dimension [{dim_type}], which is not provided"),
+                            )
+                    })
+                    .collect::<CubteraResult<Vec<&Dim>>>()?;
+                let optional = self.dimensions.iter().filter(|dim| {
+                    manifest.opt_dims.iter().flatten().any(|dim_type| dim_type == &dim.dim_type)
+                });
+                let state_path = required
+                    .into_iter()
+                    .chain(optional)
+                    .map(|dim| dim.key_path.to_string_lossy().to_string())
+                    .collect::<Vec<String>>()
+                    .join("/");
+
+                Ok(UnitDependency {
+                    name: name.clone(),
+                    manifest,
+                    state_path,
+                })
+            })
+            .collect()
+    }
+
+    // List all unit's dimensions with their parents, ordered from the root parent to the unit dim
+    pub fn get_all_dims(&self) -> Vec<&Dim> {
+        let mut all_dims: Vec<&Dim> = Vec::new();
+        self.dimensions.iter().for_each(|dim| {
+            let mut chain = vec![dim];
+            while let Some(parent) = chain.last().and_then(|d| d.parent.as_deref()) {
+                chain.push(parent);
+            }
+            chain.into_iter().rev().for_each(|d| {
+                if !all_dims.iter().any(|x| x.dim_type == d.dim_type) {
+                    all_dims.push(d);
+                }
+            });
+        });
+        all_dims
+    }
+
+    // Saves unit outputs to the inventory of the primary (first) unit dimension
+    pub fn save_outputs(&self, outputs: Value) -> CubteraResult<String> {
+        let dim = self
+            .dimensions
+            .first()
+            .or_error(CubteraError::Unit, "Unit has no dimensions to save outputs to".into())?;
+        DimBuilder::new(&dim.dim_type, &GLOBAL_CFG.org, &self.storage)
+            .with_name(&dim.dim_name)
+            .with_context(self.context.clone())
+            .save_outputs(&self.name, outputs)?;
+        Ok(format!("{}:{}", dim.dim_type, dim.dim_name))
+    }
+
+    pub fn remove_temp_folder(&self) -> CubteraResult<()> {
         let path = self.temp_folder.clone();
         if path.exists() {
-            std::fs::remove_dir_all(&path).unwrap_or_exit("Can't remove temp folder".to_string());
+            std::fs::remove_dir_all(&path)
+                .or_error(CubteraError::Unit, "Can't remove temp folder".to_string())?;
             debug!(target: "", "Temp folder was removed: \n{:?}", path);
         }
+        Ok(())
     }
 
-    pub fn copy_files(&self) {
+    pub fn copy_files(&self) -> CubteraResult<()> {
         // define destination temp folder
         let dest_folder = self.temp_folder.clone();
         debug!(target: "unit mod", "Copying files to temp folder: \n{:?}", dest_folder);
         if !dest_folder.exists() {
             std::fs::create_dir_all(&dest_folder)
-                .unwrap_or_exit(format!("Can't create temp folder: {:?}", &dest_folder));
+                .or_error(CubteraError::Unit, format!("Can't create temp folder: {:?}", &dest_folder))?;
         }
 
         // --------- Modules --------- //
@@ -233,27 +364,9 @@ impl Unit {
             .join(&GLOBAL_CFG.modules_path);
         if !dest_folder.join("modules").exists() {
             std::os::unix::fs::symlink(modules_folder_path, dest_folder.join("modules"))
-                .unwrap_or_exit("Failed to create modules symlink".to_string());
+                .or_error(CubteraError::Unit, "Failed to create modules symlink".to_string())?;
         };
 
-        // --------- Plugins --------- //
-        // TODO: move to runner specific logic
-        // copy plugin folder from config to $HOME/.terraform.d/plugins folder if exists
-        // is plugins_path absolute => current_dir will be overwritten by join method
-        let plugins_folder_path = std::env::current_dir()
-            .unwrap()
-            .join(&GLOBAL_CFG.plugins_path);
-        if plugins_folder_path.exists() {
-            let home_dir = std::env::var("HOME").unwrap();
-            copy_folder(
-                plugins_folder_path,
-                &Path::new(&home_dir).join(".terraform.d/plugins"),
-                false,
-            );
-        } else {
-            warn!(target: "", "Plugin folder {plugins_folder_path:?} does not exist. Check cubtera config. Passed...");
-        }
-
         // --------- Unit --------- //
         // self.manifest.overrides.then( ||
         //     self.generic_unit_folder.clone().map(|source_folder|
@@ -264,44 +377,41 @@ impl Unit {
         if self.manifest.overwrite {
             // copy generic unit files to temp folder if set overrides: true in unit_manifest
             if let Some(generic_unit_folder) = self.generic_unit_folder.clone() {
-                copy_folder(generic_unit_folder, &dest_folder, true);
+                copy_folder(generic_unit_folder, &dest_folder, true)
+                    .or_error(CubteraError::Unit, "Failed to copy generic unit files".to_string())?;
             };
         }
 
-        copy_folder(self.unit_folder.clone(), &dest_folder, true);
+        copy_folder(self.unit_folder.clone(), &dest_folder, true)
+            .or_error(CubteraError::Unit, "Failed to copy unit files".to_string())?;
 
         // Generate ALL Opt Dim json files with NULL values for each unit's optional dimension
-        self.opt_dims.iter().for_each(|dim| {
-            dim.iter().for_each(|opt_dim| {
-                opt_dim
-                    .save_json_dim_vars(dest_folder.clone())
-                    .unwrap_or_exit(format!(
-                        "Failed to save json dim vars for dim: {:?}",
-                        &dest_folder
-                    ));
-            });
-        });
+        for opt_dim in self.opt_dims.iter().flatten() {
+            opt_dim
+                .save_json_dim_vars(dest_folder.clone())
+                .or_error(
+                    CubteraError::Unit,
+                    format!("Failed to save json dim vars for dim: {:?}", &dest_folder),
+                )?;
+        }
 
         // Generate Dim Variables json files with json values for each unit's dimension
-        self.dimensions.iter().for_each(|dim| {
-            dim.save_json_dim_vars(dest_folder.clone())
-                .unwrap_or_exit(format!(
-                    "Failed to save json dim vars for dim {}",
-                    &dim.dim_name
-                ));
-
-            dim.save_dim_includes(dest_folder.clone())
-                .unwrap_or_exit(format!(
-                    "Failed to save dim includes for dim {}",
-                    &dim.dim_name
-                ));
-
-            dim.save_dim_folders(dest_folder.clone())
-                .unwrap_or_exit(format!(
-                    "Failed to save dim folders for dim {}",
-                    &dim.dim_name
-                ));
-        });
+        for dim in &self.dimensions {
+            dim.save_json_dim_vars(dest_folder.clone()).or_error(
+                CubteraError::Unit,
+                format!("Failed to save json dim vars for dim {}", &dim.dim_name),
+            )?;
+
+            dim.save_dim_includes(dest_folder.clone()).or_error(
+                CubteraError::Unit,
+                format!("Failed to save dim includes for dim {}", &dim.dim_name),
+            )?;
+
+            dim.save_dim_folders(dest_folder.clone()).or_error(
+                CubteraError::Unit,
+                format!("Failed to save dim folders for dim {}", &dim.dim_name),
+            )?;
+        }
 
         if !&self.extensions.is_empty() {
             let mut ext_tf_vars = String::new();
@@ -320,7 +430,7 @@ impl Unit {
             ext_tf_vars.pop();
             ext_tf_vars.push_str("\n}\n");
             std::fs::write(dest_folder.join("cubtera_ext.json"), ext_tf_vars)
-                .unwrap_or_exit("Failed to write cubtera_ext.json file".to_string());
+                .or_error(CubteraError::Unit, "Failed to write cubtera_ext.json file".to_string())?;
         }
 
         // --------- Files form Unit Manifest --------- //
@@ -329,21 +439,22 @@ impl Unit {
             if let Some(spec_files) = &spec.files {
                 if let Some(required) = spec_files.required.clone() {
                     copy_files_from_manifest(required, &dest_folder, |src| {
-                        exit_with_error(format!(
+                        Err(CubteraError::Unit(format!(
                             "Required file {} from unit manifest doesn't exist.",
                             src.red()
-                        ))
-                    });
+                        )))
+                    })?;
                 };
                 if let Some(optional) = spec_files.optional.clone() {
-                    copy_files_from_manifest(
-                        optional,
-                        &dest_folder,
-                        |src| warn!(target:"", "Optional file {} from unit manifest doesn't exist. Passed...", src.blue()),
-                    );
+                    copy_files_from_manifest(optional, &dest_folder, |src| {
+                        warn!(target:"", "Optional file {} from unit manifest doesn't exist. Passed...", src.blue());
+                        Ok(())
+                    })?;
                 };
             }
         }
+
+        Ok(())
     }
 
     pub fn get_name(&self) -> String {
@@ -391,20 +502,47 @@ impl Unit {
 fn copy_files_from_manifest(
     files: HashMap<String, String>,
     unit_folder: &Path,
-    f: impl Fn(String),
-) {
+    on_missing: impl Fn(String) -> CubteraResult<()>,
+) -> CubteraResult<()> {
     for (src, dst) in files {
         let src_path = string_to_path(&src);
         if PathBuf::new().join(&src_path).exists() {
             let dest = unit_folder.join(dst);
             if !dest.exists() {
-                std::fs::create_dir_all(dest.parent().unwrap())
-                    .unwrap_or_exit(format!("Failed to create {dest:?} file"));
+                std::fs::create_dir_all(dest.parent().unwrap_or(unit_folder))
+                    .or_error(CubteraError::Unit, format!("Failed to create {dest:?} file"))?;
             }
             std::fs::copy(&src_path, dest)
-                .unwrap_or_exit(format!("Failed to copy {} file", &src.red()));
+                .or_error(CubteraError::Unit, format!("Failed to copy {} file", &src.red()))?;
         } else {
-            f(src);
+            on_missing(src)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+impl Unit {
+    // Unit of the type without dimensions, for runner tests
+    pub(crate) fn for_test(
+        name: &str,
+        unit_type: &str,
+        unit_folder: &Path,
+        temp_folder: &Path,
+    ) -> Self {
+        let manifest = serde_json::from_value(json!({ "dimensions": [], "type": unit_type }))
+            .expect("valid test manifest");
+        Unit {
+            name: name.into(),
+            manifest,
+            temp_folder: temp_folder.to_path_buf(),
+            extensions: vec![],
+            dimensions: vec![],
+            opt_dims: None,
+            unit_folder: unit_folder.to_path_buf(),
+            generic_unit_folder: None,
+            storage: Storage::default(),
+            context: None,
         }
     }
 }
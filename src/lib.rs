@@ -8,6 +8,7 @@ mod globals {
 pub mod prelude {
     pub use crate::core::dim::*;
     pub use crate::core::dlog::*;
+    pub use crate::core::error::*;
     pub use crate::core::im::*;
     pub use crate::core::runner::*;
     pub use crate::core::unit::*;
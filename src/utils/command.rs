@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+/// Command from config or unit manifest: a string split with shell quoting rules
+/// (`"jq '.a | .b' file.json"`) or a TOML array passed as exact argv (`["jq", ".a | .b", "file.json"]`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CommandLine {
+    Line(String),
+    Argv(Vec<String>),
+}
+
+impl CommandLine {
+    // Program and args, string is split like shell does without expansions
+    pub fn argv(&self) -> Result<Vec<String>, String> {
+        match self {
+            CommandLine::Line(line) => {
+                shlex::split(line).ok_or(format!("Can't parse command, unbalanced quotes: {line}"))
+            }
+            CommandLine::Argv(argv) => Ok(argv.clone()),
+        }
+    }
+
+    // Command string for `sh -c`, string is passed as is, argv is quoted
+    pub fn shell_line(&self) -> String {
+        match self {
+            CommandLine::Line(line) => line.clone(),
+            CommandLine::Argv(argv) => quote_args(argv),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            CommandLine::Line(line) => line.trim().is_empty(),
+            CommandLine::Argv(argv) => argv.is_empty(),
+        }
+    }
+}
+
+impl Display for CommandLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.shell_line())
+    }
+}
+
+impl From<&str> for CommandLine {
+    fn from(line: &str) -> Self {
+        CommandLine::Line(line.into())
+    }
+}
+
+// Joins args to a string safe to pass to shell, e.g. CUBTERA_RUNNER_CMD env var
+pub fn quote_args(args: &[String]) -> String {
+    args.iter()
+        .map(|arg| {
+            shlex::try_quote(arg)
+                .map(|quoted| quoted.into_owned())
+                .unwrap_or_else(|_| arg.replace('\0', ""))
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argv() {
+        let command = CommandLine::from(r#"jq -r '.items[] | .name' "my file.json""#);
+        assert_eq!(
+            command.argv().unwrap(),
+            ["jq", "-r", ".items[] | .name", "my file.json"]
+        );
+        assert!(CommandLine::from("echo 'unbalanced").argv().is_err());
+
+        let command: CommandLine = toml::from_str::<toml::Table>(r#"cmd = ["echo", "a b"]"#)
+            .unwrap()["cmd"]
+            .clone()
+            .try_into()
+            .unwrap();
+        assert_eq!(command.argv().unwrap(), ["echo", "a b"]);
+    }
+
+    #[test]
+    fn test_shell_line() {
+        let command = CommandLine::Argv(vec!["echo".into(), "a b".into(), "it's".into()]);
+        assert_eq!(command.shell_line(), r#"echo 'a b' "it's""#);
+        assert_eq!(
+            CommandLine::from("cat f | grep x").shell_line(),
+            "cat f | grep x"
+        );
+        assert_eq!(
+            quote_args(&["plan".into(), "-var=a=b c".into()]),
+            "plan '-var=a=b c'"
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::core::error::{CubteraError, CubteraResult, ResultExtError};
 use log::{debug, error, warn};
 use serde_json::Value;
 use std::collections::HashMap;
@@ -89,16 +90,12 @@ fn order_json(value: &Value) -> Value {
     }
 }
 
-pub fn db_connect(db: &str) -> mongodb::sync::Client {
-    let options = match mongodb::options::ClientOptions::parse(db).run() {
-        Ok(client) => client,
-        Err(e) => exit_with_error(format!("DB connection error: {e}")),
-    };
-    let client = mongodb::sync::Client::with_options(options);
-    match client {
-        Ok(client) => client,
-        Err(e) => exit_with_error(format!("DB connection error: {e}")),
-    }
+pub fn db_connect(db: &str) -> CubteraResult<mongodb::sync::Client> {
+    let options = mongodb::options::ClientOptions::parse(db)
+        .run()
+        .or_error(CubteraError::DataSource, "DB connection error".into())?;
+    mongodb::sync::Client::with_options(options)
+        .or_error(CubteraError::DataSource, "DB connection error".into())
 }
 
 /// Exits the program with an error message and a status code of 1.
@@ -186,20 +183,20 @@ pub fn convert_path_to_absolute(s: String) -> Option<String> {
 ///
 /// # Returns
 ///
-/// An `Option` containing the `serde_json::Value` object if the file exists and is a valid JSON file,
-/// otherwise `None`.
-pub fn read_json_file(path: &PathBuf) -> Option<Value> {
+/// `Some` with the `serde_json::Value` object if the file exists and is a valid JSON file,
+/// `None` if the file doesn't exist, or an error if the file is not a valid JSON file.
+pub fn read_json_file(path: &PathBuf) -> CubteraResult<Option<Value>> {
     match std::fs::read_to_string(path) {
         Ok(json) => {
-            let meta_data = serde_json::from_str::<Value>(&json).unwrap_or_exit(format!(
-                "Sorry, but file {:?} is not a valid JSON file",
-                &path
-            ));
-            Some(meta_data)
+            let meta_data = serde_json::from_str::<Value>(&json).or_error(
+                CubteraError::DataSource,
+                format!("Sorry, but file {:?} is not a valid JSON file", &path),
+            )?;
+            Ok(Some(meta_data))
         }
         Err(_) => {
-            debug!(target:"", "File {:?} doesn't exist. Pass empty data...", &path.file_name().unwrap());
-            None
+            debug!(target:"", "File {:?} doesn't exist. Pass empty data...", &path.file_name().unwrap_or_default());
+            Ok(None)
         }
     }
 }
@@ -281,7 +278,6 @@ pub fn merge_values(data: &mut serde_json::Value, with: &serde_json::Value) {
 }
 
 use std::collections::HashSet;
-use std::process::ExitStatus;
 
 pub fn if_intersect(vec1: Vec<String>, vec2: Vec<String>) -> bool {
     let set1: HashSet<String> = vec1.into_iter().collect();
@@ -409,10 +405,13 @@ pub fn read_and_validate_json(json_path: PathBuf, schema_path: PathBuf) -> Optio
 ///
 /// * `src` - A `PathBuf` representing the source folder path.
 /// * `dst` - A reference to a `PathBuf` representing the destination folder path.
-pub fn copy_all_files_in_folder(src: PathBuf, dst: &PathBuf, overwrite_existing: bool) {
+pub fn copy_all_files_in_folder(
+    src: PathBuf,
+    dst: &PathBuf,
+    overwrite_existing: bool,
+) -> std::io::Result<()> {
     if !dst.exists() {
-        std::fs::create_dir_all(dst)
-            .unwrap_or_exit(format!("Failed to create folder {}", dst.to_str().unwrap()));
+        std::fs::create_dir_all(dst)?;
     }
     walkdir::WalkDir::new(src)
         .max_depth(1)
@@ -421,12 +420,16 @@ pub fn copy_all_files_in_folder(src: PathBuf, dst: &PathBuf, overwrite_existing:
         .filter(|e| e.file_type().is_file())
         //.filter(|_| override_existing)
         .filter(|path| !(dst.join(path.file_name()).exists()) || overwrite_existing)
-        .for_each(|e| {
+        .try_for_each(|e| {
             let src_path = e.clone().into_path();
-            let dst_path = dst.join(e.into_path().file_name().unwrap());
-            std::fs::copy(&src_path, &dst_path)
-                .unwrap_or_exit(format!("Failed to copy file {src_path:?} to {dst_path:?}"));
-        });
+            let dst_path = dst.join(e.into_path().file_name().unwrap_or_default());
+            std::fs::copy(&src_path, &dst_path).map(|_| ()).map_err(|err| {
+                std::io::Error::new(
+                    err.kind(),
+                    format!("Failed to copy file {src_path:?} to {dst_path:?}: {err}"),
+                )
+            })
+        })
 }
 
 /// Recursively copies all files and subfolders from the source folder to the destination folder.
@@ -435,8 +438,8 @@ pub fn copy_all_files_in_folder(src: PathBuf, dst: &PathBuf, overwrite_existing:
 ///
 /// * `src` - A `PathBuf` representing the source folder to copy from.
 /// * `dst` - A reference to a `PathBuf` representing the destination folder to copy to.
-pub fn copy_folder(src: PathBuf, dst: &PathBuf, overwrite_existing: bool) {
-    copy_all_files_in_folder(src.clone(), dst, overwrite_existing);
+pub fn copy_folder(src: PathBuf, dst: &PathBuf, overwrite_existing: bool) -> std::io::Result<()> {
+    copy_all_files_in_folder(src.clone(), dst, overwrite_existing)?;
 
     walkdir::WalkDir::new(src)
         .max_depth(1)
@@ -444,13 +447,13 @@ pub fn copy_folder(src: PathBuf, dst: &PathBuf, overwrite_existing: bool) {
         .filter_map(Result::ok)
         .filter(|path| path.path().is_dir())
         .skip(1)
-        .for_each(|folder| {
+        .try_for_each(|folder| {
             copy_folder(
                 folder.clone().into_path(),
-                &dst.join(folder.into_path().file_name().unwrap()),
+                &dst.join(folder.into_path().file_name().unwrap_or_default()),
                 overwrite_existing,
-            );
-        });
+            )
+        })
 }
 
 pub fn check_path(path: PathBuf) -> Option<PathBuf> {
@@ -494,23 +497,31 @@ pub fn string_to_path(s: &str) -> PathBuf {
     PathBuf::from(with_env)
 }
 
+// Runs command as argv or with `/bin/sh -c` in shell mode (pipes, redirects, env expansion)
 pub fn execute_command(
-    command: &str,
+    command: &crate::utils::command::CommandLine,
+    shell: bool,
     current_dir: &str,
     env_vars: HashMap<String, String>,
-) -> Result<ExitStatus, Box<dyn std::error::Error>> {
-    let mut command = command.split_whitespace();
-    let binary = command.next().unwrap_or_exit("Command is empty".into());
-    let path = string_to_path(binary);
-    let args = command.collect::<Vec<&str>>();
+    options: &crate::utils::process::ProcessOptions,
+) -> Result<crate::utils::process::ProcessResult, Box<dyn std::error::Error>> {
+    if command.is_empty() {
+        return Err("Command is empty".into());
+    }
 
-    let mut process = std::process::Command::new(path)
-        .current_dir(current_dir)
-        .args(args)
-        .envs(env_vars)
-        .spawn()?;
+    let mut process = if shell {
+        let mut process = std::process::Command::new("/bin/sh");
+        process.arg("-c").arg(command.shell_line());
+        process
+    } else {
+        let argv = command.argv()?;
+        let mut process = std::process::Command::new(string_to_path(&argv[0]));
+        process.args(&argv[1..]);
+        process
+    };
+    process.current_dir(current_dir).envs(env_vars);
 
-    let result = process.wait();
+    let result = crate::utils::process::run_process(&mut process, options);
 
     match result {
         Ok(status) => Ok(status),
@@ -542,7 +553,7 @@ fn test_copy_all_files_in_folder() {
     let dst_path = dst_dir.path();
 
     // Copy the files from the source directory to the destination directory
-    copy_all_files_in_folder(src_dir.path().to_path_buf(), &dst_path.to_path_buf(), true);
+    copy_all_files_in_folder(src_dir.path().to_path_buf(), &dst_path.to_path_buf(), true).unwrap();
 
     // Check that the files were copied correctly
     let copied_file1_path = dst_path.join("file1.txt");
@@ -581,7 +592,7 @@ fn test_copy_folder() {
     let dst_path = Path::new(dst_dir.path());
 
     // Call the function to copy the source directory to the destination directory.
-    copy_folder(src_path.to_path_buf(), &dst_path.to_path_buf(), true);
+    copy_folder(src_path.to_path_buf(), &dst_path.to_path_buf(), true).unwrap();
 
     // Assert that the files and subdirectories were copied successfully.
     let dst_file1_path = dst_path.join("file1.txt");
@@ -0,0 +1,181 @@
+use log::{debug, info, warn};
+use rand::Rng;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::utils::helper::string_to_path;
+
+const LOCKS_PATH: &str = "~/.cubtera/locks";
+
+/// Advisory file lock (`flock`) in `~/.cubtera/locks/<name>.lock`.
+///
+/// The lock is released on drop or by the OS when the holder process dies, so a crashed
+/// run never blocks other runs. Holder info (pid, host, time) is written to the lock file
+/// and cleared on release: a lock file with holder info which is not locked anymore
+/// is a stale leftover of a killed process and is taken over.
+pub struct FileLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Tries to acquire the lock without waiting. Returns `None` if the lock is held by another run.
+    pub fn try_acquire(name: &str) -> std::io::Result<Option<Self>> {
+        Self::try_acquire_in(&string_to_path(LOCKS_PATH), name)
+    }
+
+    /// Waits for the lock until timeout is reached.
+    pub fn acquire(name: &str, timeout: Duration) -> std::io::Result<Self> {
+        Self::acquire_in(&string_to_path(LOCKS_PATH), name, timeout)
+    }
+
+    fn try_acquire_in(folder: &Path, name: &str) -> std::io::Result<Option<Self>> {
+        std::fs::create_dir_all(folder)?;
+        let path = lock_file_path(folder, name);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            let err = std::io::Error::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::EWOULDBLOCK) => Ok(None),
+                _ => Err(err),
+            };
+        }
+
+        let mut previous_holder = String::new();
+        file.read_to_string(&mut previous_holder)?;
+        if !previous_holder.trim().is_empty() {
+            warn!(target: "lock", "Stale lock {:?} was taken over. Previous holder: {}", path, previous_holder.trim());
+        }
+
+        file.set_len(0)?;
+        file.rewind()?;
+        file.write_all(holder_info().as_bytes())?;
+        file.flush()?;
+
+        debug!(target: "lock", "Lock {:?} acquired", path);
+        Ok(Some(FileLock { file, path }))
+    }
+
+    fn acquire_in(folder: &Path, name: &str, timeout: Duration) -> std::io::Result<Self> {
+        let start = Instant::now();
+        let mut notified = false;
+
+        loop {
+            if let Some(lock) = Self::try_acquire_in(folder, name)? {
+                return Ok(lock);
+            }
+            if !notified {
+                info!(target: "lock", "Waiting for lock {}. Holder: {}", name, read_holder(folder, name));
+                notified = true;
+            }
+            if start.elapsed() >= timeout {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::TimedOut,
+                    format!(
+                        "Timeout waiting for lock {} after {}s. Holder: {}",
+                        name,
+                        timeout.as_secs(),
+                        read_holder(folder, name)
+                    ),
+                ));
+            }
+            let delay = rand::rng().random_range(300..700);
+            std::thread::sleep(Duration::from_millis(delay));
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // clear holder info first, so the lock file left behind is not reported as stale
+        let _ = self.file.set_len(0);
+        unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_UN) };
+        debug!(target: "lock", "Lock {:?} released", self.path);
+    }
+}
+
+fn lock_file_path(folder: &Path, name: &str) -> PathBuf {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    folder.join(format!("{name}.lock"))
+}
+
+fn holder_info() -> String {
+    format!(
+        "pid={} host={} time={}",
+        std::process::id(),
+        whoami::fallible::hostname().unwrap_or("undefined".into()),
+        chrono::Utc::now().to_rfc3339(),
+    )
+}
+
+fn read_holder(folder: &Path, name: &str) -> String {
+    std::fs::read_to_string(lock_file_path(folder, name))
+        .ok()
+        .map(|holder| holder.trim().to_string())
+        .filter(|holder| !holder.is_empty())
+        .unwrap_or("unknown".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let lock = FileLock::try_acquire_in(dir.path(), "init").unwrap();
+        assert!(lock.is_some());
+        assert!(FileLock::try_acquire_in(dir.path(), "init")
+            .unwrap()
+            .is_none());
+        assert!(FileLock::try_acquire_in(dir.path(), "other")
+            .unwrap()
+            .is_some());
+
+        drop(lock);
+        assert!(FileLock::try_acquire_in(dir.path(), "init")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_lock_timeout() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let _lock = FileLock::try_acquire_in(dir.path(), "terraform-1.6.6").unwrap();
+        let result = FileLock::acquire_in(dir.path(), "terraform-1.6.6", Duration::from_millis(10));
+        assert_eq!(result.err().unwrap().kind(), std::io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(lock_file_path(dir.path(), "init"), "pid=1 host=gone").unwrap();
+
+        let lock = FileLock::acquire_in(dir.path(), "init", Duration::from_secs(1)).unwrap();
+        let holder = read_holder(dir.path(), "init");
+        assert!(holder.starts_with(&format!("pid={}", std::process::id())));
+
+        drop(lock);
+        assert_eq!(read_holder(dir.path(), "init"), "unknown");
+    }
+}
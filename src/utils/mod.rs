@@ -1,4 +1,9 @@
+pub mod command;
 pub mod helper;
+pub mod lock;
+pub mod output;
+pub mod process;
+pub mod snapshot;
 
 pub fn logger_init() {
     env_logger::builder()
@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Output capture settings: child stdout/stderr are copied to the terminal and appended to the log file.
+#[derive(Debug, Clone)]
+pub struct OutputCapture {
+    pub path: PathBuf,
+    pub strip_ansi: bool,
+}
+
+impl OutputCapture {
+    pub fn new(path: PathBuf, strip_ansi: bool) -> Self {
+        OutputCapture { path, strip_ansi }
+    }
+}
+
+/// Starts threads which tee piped child stdout/stderr to the terminal and the capture log file.
+pub fn capture_output(
+    child: &mut Child,
+    capture: &OutputCapture,
+) -> std::io::Result<Vec<JoinHandle<std::io::Result<()>>>> {
+    let log = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&capture.path)?;
+    let log = Arc::new(Mutex::new(log));
+    let strip_ansi = capture.strip_ansi;
+
+    let mut handles = Vec::new();
+    if let Some(out) = child.stdout.take() {
+        let log = log.clone();
+        handles.push(std::thread::spawn(move || {
+            tee(out, std::io::stdout(), log, strip_ansi)
+        }));
+    }
+    if let Some(err) = child.stderr.take() {
+        let log = log.clone();
+        handles.push(std::thread::spawn(move || {
+            tee(err, std::io::stderr(), log, strip_ansi)
+        }));
+    }
+
+    Ok(handles)
+}
+
+/// Returns last `lines` lines of the file.
+pub fn tail(path: &Path, lines: usize) -> std::io::Result<String> {
+    let content = std::fs::read_to_string(path)?;
+    let tail = content
+        .lines()
+        .rev()
+        .take(lines)
+        .collect::<Vec<&str>>()
+        .into_iter()
+        .rev()
+        .collect::<Vec<&str>>()
+        .join("\n");
+    Ok(tail)
+}
+
+// Terminal gets raw chunks as soon as they are read (progress output without new lines),
+// log file gets complete lines, so escape sequences are not split between chunks
+fn tee(
+    mut source: impl Read,
+    mut terminal: impl Write,
+    log: Arc<Mutex<File>>,
+    strip_ansi: bool,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    let mut line = Vec::new();
+
+    let write_log = |line: &[u8]| {
+        let data = match strip_ansi {
+            true => strip_ansi_codes(line),
+            false => line.to_vec(),
+        };
+        if let Ok(mut log) = log.lock() {
+            let _ = log.write_all(&data);
+        }
+    };
+
+    loop {
+        let size = source.read(&mut buffer)?;
+        if size == 0 {
+            break;
+        }
+        let _ = terminal.write_all(&buffer[..size]);
+        let _ = terminal.flush();
+
+        line.extend_from_slice(&buffer[..size]);
+        if let Some(pos) = line.iter().rposition(|b| *b == b'\n') {
+            let rest = line.split_off(pos + 1);
+            write_log(&line);
+            line = rest;
+        }
+    }
+
+    if !line.is_empty() {
+        write_log(&line);
+    }
+
+    Ok(())
+}
+
+// Removes CSI (ESC [ ... final byte) and OSC (ESC ] ... BEL or ESC \) sequences
+pub fn strip_ansi_codes(data: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(data.len());
+    let mut iter = data.iter().copied().peekable();
+
+    while let Some(byte) = iter.next() {
+        if byte != 0x1b {
+            result.push(byte);
+            continue;
+        }
+        match iter.next() {
+            Some(b'[') => {
+                for b in iter.by_ref() {
+                    if (0x40..=0x7e).contains(&b) {
+                        break;
+                    }
+                }
+            }
+            Some(b']') => {
+                while let Some(b) = iter.next() {
+                    if b == 0x07 || (b == 0x1b && iter.next_if_eq(&b'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi_codes() {
+        let colored = b"\x1b[1m\x1b[32mApply complete!\x1b[0m Resources: 1 added\n";
+        assert_eq!(
+            strip_ansi_codes(colored),
+            b"Apply complete! Resources: 1 added\n"
+        );
+
+        let link = b"\x1b]8;;http://x\x07link\x1b]8;;\x1b\\ text";
+        assert_eq!(strip_ansi_codes(link), b"link text");
+    }
+
+    #[test]
+    fn test_capture_output() {
+        use crate::utils::process::{run_process, ProcessOptions};
+
+        let dir = tempfile::tempdir().unwrap();
+        let capture = OutputCapture::new(dir.path().join("output.log"), true);
+        let options = ProcessOptions {
+            capture: Some(capture.clone()),
+            ..Default::default()
+        };
+
+        let result = run_process(
+            std::process::Command::new("sh")
+                .arg("-c")
+                .arg("printf '\\033[31mout\\033[0m\\n'; echo err >&2; exit 3"),
+            &options,
+        )
+        .unwrap();
+
+        assert_eq!(result.code(), 3);
+        let log = std::fs::read_to_string(&capture.path).unwrap();
+        assert!(log.contains("out\n"));
+        assert!(log.contains("err\n"));
+        assert!(!log.contains('\x1b'));
+    }
+
+    #[test]
+    fn test_tail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("output.log");
+        std::fs::write(&path, "1\n2\n3\n4\n").unwrap();
+
+        assert_eq!(tail(&path, 2).unwrap(), "3\n4");
+        assert_eq!(tail(&path, 10).unwrap(), "1\n2\n3\n4");
+    }
+}
@@ -0,0 +1,278 @@
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::Once;
+use std::time::{Duration, Instant};
+
+use crate::utils::output::{capture_output, OutputCapture};
+
+// Signal handler state, shared by all running child processes
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static SIGNALS: AtomicUsize = AtomicUsize::new(0);
+static LAST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+static LAST_FORWARD: AtomicBool = AtomicBool::new(false);
+static HANDLERS: Once = Once::new();
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Child process options: output capture, timeout and grace period before SIGKILL.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOptions {
+    pub capture: Option<OutputCapture>,
+    pub timeout: Option<Duration>,
+    pub grace_period: Duration,
+}
+
+/// Reason why the child process was stopped by cubtera.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Termination {
+    Cancelled(i32),
+    Timeout(Duration),
+}
+
+impl Termination {
+    /// Exit code in shell convention: 128 + signal for cancellation, 124 for timeout.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Termination::Cancelled(signal) => 128 + signal,
+            Termination::Timeout(_) => TIMEOUT_EXIT_CODE,
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Termination::Cancelled(_) => "cancelled",
+            Termination::Timeout(_) => "timeout",
+        }
+    }
+}
+
+impl std::fmt::Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Termination::Cancelled(signal) => write!(f, "cancelled by {}", signal_name(*signal)),
+            Termination::Timeout(timeout) => write!(f, "timeout after {}s", timeout.as_secs()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ProcessResult {
+    pub status: ExitStatus,
+    pub termination: Option<Termination>,
+}
+
+impl ProcessResult {
+    /// Process exit code, termination exit code if the process was stopped by cubtera.
+    pub fn code(&self) -> i32 {
+        self.termination
+            .as_ref()
+            .map(Termination::exit_code)
+            .unwrap_or(self.status.code().unwrap_or(1))
+    }
+
+    pub fn success(&self) -> bool {
+        self.termination.is_none() && self.status.success()
+    }
+}
+
+/// Spawns the command and waits for it to finish.
+///
+/// SIGINT/SIGTERM received by cubtera are forwarded to the child, so it can shut down gracefully
+/// (e.g. terraform releases the state lock). The child is killed if it is still running after
+/// the grace period. Timeout stops the child the same way with SIGTERM.
+pub fn run_process(
+    command: &mut Command,
+    options: &ProcessOptions,
+) -> std::io::Result<ProcessResult> {
+    HANDLERS.call_once(install_signal_handlers);
+
+    if options.capture.is_some() {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+
+    let _running = RunningGuard::new();
+    let mut child = command.spawn()?;
+    let handles = match &options.capture {
+        Some(capture) => capture_output(&mut child, capture)?,
+        None => vec![],
+    };
+
+    let result = wait(&mut child, options);
+    for handle in handles {
+        let _ = handle.join();
+    }
+
+    result
+}
+
+fn wait(child: &mut Child, options: &ProcessOptions) -> std::io::Result<ProcessResult> {
+    let start = Instant::now();
+    let mut seen_signals = SIGNALS.load(Ordering::SeqCst);
+    let mut termination: Option<Termination> = None;
+    let mut kill_at: Option<Instant> = None;
+
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(ProcessResult {
+                status,
+                termination,
+            });
+        }
+
+        let signals = SIGNALS.load(Ordering::SeqCst);
+        if signals != seen_signals {
+            seen_signals = signals;
+            let signal = LAST_SIGNAL.load(Ordering::SeqCst);
+            if LAST_FORWARD.load(Ordering::SeqCst) {
+                send_signal(child, signal);
+            }
+            if termination.is_none() {
+                log::warn!(target: "process", "Received {}, waiting {}s for the process to finish",
+                    signal_name(signal), options.grace_period.as_secs());
+                termination = Some(Termination::Cancelled(signal));
+                kill_at = Some(Instant::now() + options.grace_period);
+            }
+        }
+
+        if let Some(timeout) = options.timeout {
+            if termination.is_none() && start.elapsed() >= timeout {
+                log::warn!(target: "process", "Timeout after {}s, stopping the process", timeout.as_secs());
+                send_signal(child, libc::SIGTERM);
+                termination = Some(Termination::Timeout(timeout));
+                kill_at = Some(Instant::now() + options.grace_period);
+            }
+        }
+
+        if kill_at.is_some_and(|at| Instant::now() >= at) {
+            log::warn!(target: "process", "Grace period is over, killing the process");
+            child.kill()?;
+            kill_at = None;
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn send_signal(child: &Child, signal: i32) {
+    unsafe { libc::kill(child.id() as libc::pid_t, signal) };
+}
+
+fn signal_name(signal: i32) -> String {
+    match signal {
+        libc::SIGINT => "SIGINT".into(),
+        libc::SIGTERM => "SIGTERM".into(),
+        _ => format!("signal {signal}"),
+    }
+}
+
+// Counts running child processes, so signal handler knows if there is anything to wait for
+struct RunningGuard;
+
+impl RunningGuard {
+    fn new() -> Self {
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        RunningGuard
+    }
+}
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+extern "C" fn on_signal(signal: libc::c_int, info: *mut libc::siginfo_t, _: *mut libc::c_void) {
+    // nothing to wait for, exit as with default handler
+    if RUNNING.load(Ordering::SeqCst) == 0 {
+        unsafe { libc::_exit(128 + signal) };
+    }
+    // terminal signals (Ctrl-C) are delivered to the whole foreground process group, child included.
+    // Only signals sent by other processes (kill, CI job cancellation) have to be forwarded
+    let sent_by_process = info.is_null() || unsafe { (*info).si_code } <= 0;
+    LAST_SIGNAL.store(signal, Ordering::SeqCst);
+    LAST_FORWARD.store(sent_by_process, Ordering::SeqCst);
+    SIGNALS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn install_signal_handlers() {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = on_signal as *const () as usize;
+        action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        for signal in [libc::SIGINT, libc::SIGTERM] {
+            libc::sigaction(signal, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(timeout: Option<Duration>, grace_period: Duration) -> ProcessOptions {
+        ProcessOptions {
+            capture: None,
+            timeout,
+            grace_period,
+        }
+    }
+
+    #[test]
+    fn test_run_process_exit_code() {
+        let result = run_process(
+            Command::new("sh").args(["-c", "exit 3"]),
+            &options(None, Duration::from_secs(1)),
+        )
+        .unwrap();
+
+        assert_eq!(result.code(), 3);
+        assert!(result.termination.is_none());
+        assert!(!result.success());
+    }
+
+    #[test]
+    fn test_run_process_timeout() {
+        let start = Instant::now();
+        let result = run_process(
+            Command::new("sleep").arg("10"),
+            &options(Some(Duration::from_millis(200)), Duration::from_secs(5)),
+        )
+        .unwrap();
+
+        assert_eq!(
+            result.termination,
+            Some(Termination::Timeout(Duration::from_millis(200)))
+        );
+        assert_eq!(result.code(), 124);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_run_process_kill_after_grace_period() {
+        let start = Instant::now();
+        let result = run_process(
+            Command::new("sh").args(["-c", "trap '' TERM; while true; do sleep 0.1; done"]),
+            &options(Some(Duration::from_millis(200)), Duration::from_millis(300)),
+        )
+        .unwrap();
+
+        assert_eq!(result.termination.unwrap().reason(), "timeout");
+        assert!(result.status.code().is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_termination_display() {
+        assert_eq!(
+            Termination::Cancelled(libc::SIGINT).to_string(),
+            "cancelled by SIGINT"
+        );
+        assert_eq!(Termination::Cancelled(libc::SIGTERM).exit_code(), 143);
+        assert_eq!(
+            Termination::Timeout(Duration::from_secs(600)).to_string(),
+            "timeout after 600s"
+        );
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Bigger files are listed but not compared by content
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+// Line diff is skipped for files with more lines than this (old * new)
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Files of a folder with their content, e.g. unit temp folder before and after render.
+///
+/// Symlinks (modules) and `.terraform` folder are skipped.
+#[derive(Debug, Clone, Default)]
+pub struct FolderSnapshot {
+    files: BTreeMap<String, Option<Vec<u8>>>, // relative path -> content, None for big files
+}
+
+impl FolderSnapshot {
+    /// Reads the folder recursively, missing folder is an empty snapshot.
+    pub fn read(dir: &Path) -> Self {
+        let files = walkdir::WalkDir::new(dir)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".terraform")
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let path = entry
+                    .path()
+                    .strip_prefix(dir)
+                    .ok()?
+                    .to_string_lossy()
+                    .to_string();
+                let content = entry
+                    .metadata()
+                    .ok()
+                    .filter(|meta| meta.len() <= MAX_FILE_SIZE)
+                    .and_then(|_| std::fs::read(entry.path()).ok());
+                Some((path, content))
+            })
+            .collect();
+
+        FolderSnapshot { files }
+    }
+
+    /// Snapshot of files given by relative path and content, e.g. rendered before writing.
+    pub fn from_files(files: impl IntoIterator<Item = (String, Vec<u8>)>) -> Self {
+        let files = files
+            .into_iter()
+            .map(|(path, content)| {
+                let content = (content.len() as u64 <= MAX_FILE_SIZE).then_some(content);
+                (path, content)
+            })
+            .collect();
+
+        FolderSnapshot { files }
+    }
+
+    /// Relative paths of all files, sorted.
+    pub fn paths(&self) -> Vec<&str> {
+        self.files.keys().map(String::as_str).collect()
+    }
+
+    /// Text content of the file, None for missing, big or binary files.
+    pub fn text(&self, path: &str) -> Option<&str> {
+        self.files
+            .get(path)?
+            .as_deref()
+            .and_then(|content| std::str::from_utf8(content).ok())
+    }
+
+    /// Diff from this snapshot to the new one: `+`/`-` for added and removed files,
+    /// `~` for changed ones followed by their changed lines.
+    pub fn diff(&self, new: &FolderSnapshot) -> Vec<String> {
+        let mut diff = Vec::new();
+        for path in self
+            .files
+            .keys()
+            .filter(|path| !new.files.contains_key(*path))
+        {
+            diff.push(format!("- {path}"));
+        }
+        for (path, content) in &new.files {
+            match self.files.get(path) {
+                None => diff.push(format!("+ {path}")),
+                Some(old) if old == content => {}
+                Some(_) => {
+                    diff.push(format!("~ {path}"));
+                    match (self.text(path), new.text(path)) {
+                        (Some(old), Some(new)) => diff.extend(
+                            line_diff(old, new)
+                                .into_iter()
+                                .map(|line| format!("    {line}")),
+                        ),
+                        _ => diff.push("    binary or big file changed".into()),
+                    }
+                }
+            }
+        }
+        diff
+    }
+}
+
+// Changed lines only: removed with "-", added with "+", based on longest common subsequence
+fn line_diff(old: &str, new: &str) -> Vec<String> {
+    let old = old.lines().collect::<Vec<&str>>();
+    let new = new.lines().collect::<Vec<&str>>();
+    if old.len().saturating_mul(new.len()) > MAX_DIFF_CELLS {
+        return vec!["file is too big for line diff".into()];
+    }
+
+    // lcs[i][j] - length of the common subsequence of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("main.tf"), "a\nb\nc\n").unwrap();
+        std::fs::write(dir.path().join("old.tf"), "x\n").unwrap();
+        std::fs::create_dir_all(dir.path().join(".terraform")).unwrap();
+        std::fs::write(dir.path().join(".terraform/lock"), "1").unwrap();
+        let old = FolderSnapshot::read(dir.path());
+        assert_eq!(old.paths(), ["main.tf", "old.tf"]);
+
+        std::fs::write(dir.path().join("main.tf"), "a\nB\nc\nd\n").unwrap();
+        std::fs::remove_file(dir.path().join("old.tf")).unwrap();
+        std::fs::create_dir_all(dir.path().join("sub")).unwrap();
+        std::fs::write(dir.path().join("sub/new.json"), "{}").unwrap();
+        let new = FolderSnapshot::read(dir.path());
+
+        assert_eq!(
+            old.diff(&new),
+            [
+                "- old.tf",
+                "~ main.tf",
+                "    -b",
+                "    +B",
+                "    +d",
+                "+ sub/new.json"
+            ]
+        );
+        assert!(new.diff(&new).is_empty());
+        assert!(FolderSnapshot::read(&dir.path().join("missing"))
+            .paths()
+            .is_empty());
+    }
+}